@@ -1,39 +1,72 @@
-use super::config::PlatzClientConfig;
+use super::builder::PlatzClientBuilder;
 use super::error::PlatzClientError;
+use super::provider::{CredentialProvider, ProvidedCredentials};
 use super::request::PlatzRequest;
-use async_std::sync::RwLock;
-use reqwest::Url;
-use reqwest::header::{HeaderName, HeaderValue};
+use super::source::{ConfigExplanation, CredentialSource};
+
+/// How long before expiry access tokens are refreshed by default.
+pub(super) const DEFAULT_REFRESH_SKEW: chrono::Duration = chrono::Duration::seconds(60);
 
 pub struct PlatzClient {
-    config: RwLock<PlatzClientConfig>,
+    provider: Box<dyn CredentialProvider>,
+    refresh_skew: chrono::Duration,
 }
 
 impl<'s> PlatzClient {
     pub async fn new() -> Result<Self, PlatzClientError> {
-        Ok(Self {
-            config: RwLock::new(PlatzClientConfig::new().await?),
-        })
-    }
-
-    pub(super) async fn build_url(&self, path: &str) -> Result<Url, PlatzClientError> {
-        self.config
-            .read()
-            .await
-            .server_url
-            .join(path)
-            .map_err(PlatzClientError::UrlJoinError)
-    }
-
-    pub(super) async fn authorization(
-        &self,
-    ) -> Result<(HeaderName, HeaderValue), PlatzClientError> {
-        let mut config = self.config.write().await;
-        if config.expired() {
-            *config = PlatzClientConfig::new().await?;
+        Self::builder().build().await
+    }
+
+    /// Configure how the client loads its credentials, see `PlatzClientBuilder`.
+    pub fn builder() -> PlatzClientBuilder {
+        PlatzClientBuilder::default()
+    }
+
+    /// Create a client using a named profile from the config files, ignoring
+    /// `PLATZ_PROFILE` and the default profile.
+    pub async fn new_from_profile(profile_name: &str) -> Result<Self, PlatzClientError> {
+        Self::builder().profile(profile_name).build().await
+    }
+
+    pub(super) fn from_provider(provider: Box<dyn CredentialProvider>) -> Self {
+        Self {
+            provider,
+            refresh_skew: DEFAULT_REFRESH_SKEW,
         }
+    }
+
+    /// Where the client's current credentials were loaded from.
+    pub async fn credential_source(&self) -> Result<CredentialSource, PlatzClientError> {
+        Ok(self.credentials().await?.source)
+    }
+
+    /// Report every credential source `new` tries, which one is selected
+    /// and why the others were skipped. Useful for debugging a client that
+    /// connects to the wrong server. This always explains the defaults; use
+    /// `PlatzClientBuilder::explain` for a client built with other settings.
+    pub async fn explain_config() -> ConfigExplanation {
+        Self::builder().explain().await
+    }
+
+    /// Set how long before expiry access tokens are refreshed. Defaults to
+    /// one minute.
+    pub fn with_refresh_skew(mut self, refresh_skew: chrono::Duration) -> Self {
+        self.refresh_skew = refresh_skew;
+        self
+    }
+
+    pub(super) async fn credentials(&self) -> Result<ProvidedCredentials, PlatzClientError> {
+        self.provider
+            .get_credentials(self.refresh_skew)
+            .await?
+            .ok_or(PlatzClientError::NoConfigFound)
+    }
 
-        config.get_authorization().await
+    /// Ask the credential provider for new credentials after the server
+    /// rejected the current ones. Returns whether different credentials
+    /// were loaded.
+    pub(super) async fn reload_credentials(&self) -> Result<bool, PlatzClientError> {
+        self.provider.invalidate().await
     }
 
     pub fn request<S>(&'s self, method: reqwest::Method, path: S) -> PlatzRequest<'s>
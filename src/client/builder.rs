@@ -0,0 +1,127 @@
+use super::base::{PlatzClient, DEFAULT_REFRESH_SKEW};
+use super::config::PlatzClientConfig;
+use super::config_file::ConfigLayers;
+use super::error::PlatzClientError;
+use super::provider::{ChainProvider, ConfigFileProvider, CredentialProvider, EnvProvider};
+use super::source::{ConfigAttempt, ConfigExplanation, ConfigOutcome};
+use std::path::PathBuf;
+
+/// Builds a `PlatzClient` with options other than the defaults used by
+/// `PlatzClient::new`.
+#[derive(Default)]
+pub struct PlatzClientBuilder {
+    config_path: Option<PathBuf>,
+    profile: Option<String>,
+    provider: Option<Box<dyn CredentialProvider>>,
+    strict_env: bool,
+    refresh_skew: Option<chrono::Duration>,
+}
+
+impl PlatzClientBuilder {
+    /// Load credentials only from this config file, instead of trying
+    /// environment variables, the user and project config files and a
+    /// mounted secret.
+    pub fn config_path<P>(mut self, config_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.config_path = Some(config_path.into());
+        self
+    }
+
+    /// Load credentials from this config file profile, ignoring
+    /// `PLATZ_PROFILE`, `use_profile` and the default profile.
+    pub fn profile<S>(mut self, profile: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Get credentials from a custom provider instead of the built-in
+    /// `ChainProvider::default_chain`. Takes precedence over `config_path`
+    /// and `profile`.
+    pub fn provider<P>(mut self, provider: P) -> Self
+    where
+        P: CredentialProvider + 'static,
+    {
+        self.provider = Some(Box::new(provider));
+        self
+    }
+
+    /// Fail instead of ignoring a partial or ambiguous configuration in the
+    /// environment variables, see `EnvProvider::strict`.
+    pub fn strict_env(mut self, strict_env: bool) -> Self {
+        self.strict_env = strict_env;
+        self
+    }
+
+    /// See `PlatzClient::with_refresh_skew`.
+    pub fn refresh_skew(mut self, refresh_skew: chrono::Duration) -> Self {
+        self.refresh_skew = Some(refresh_skew);
+        self
+    }
+
+    /// Report which credential source `build` would use with these
+    /// settings and why the others were skipped, see
+    /// `PlatzClient::explain_config`.
+    pub async fn explain(&self) -> ConfigExplanation {
+        match &self.provider {
+            Some(provider) => {
+                let refresh_skew = self.refresh_skew.unwrap_or(DEFAULT_REFRESH_SKEW);
+                let outcome = match provider.get_credentials(refresh_skew).await {
+                    Ok(Some(credentials)) => ConfigOutcome::Selected(credentials.source),
+                    Ok(None) => {
+                        ConfigOutcome::Skipped("the provider has no credentials".to_owned())
+                    }
+                    Err(err) => ConfigOutcome::Failed(err.to_string()),
+                };
+                ConfigExplanation {
+                    attempts: vec![ConfigAttempt {
+                        description: "Custom credential provider".to_owned(),
+                        outcome,
+                    }],
+                }
+            }
+            None if self.config_path.is_some() || self.profile.is_some() => {
+                PlatzClientConfig::explain_config_layers(
+                    self.config_path.as_deref(),
+                    self.profile.as_deref(),
+                )
+                .await
+            }
+            None => PlatzClientConfig::explain(self.strict_env).await,
+        }
+    }
+
+    /// Build the client, failing if the provider has no credentials.
+    pub async fn build(self) -> Result<PlatzClient, PlatzClientError> {
+        let provider: Box<dyn CredentialProvider> = match self.provider {
+            Some(provider) => provider,
+            None if self.config_path.is_some() || self.profile.is_some() => {
+                let layers = ConfigLayers::load(self.config_path.as_deref()).await?;
+                let config =
+                    PlatzClientConfig::from_config_layers(&layers, self.profile.as_deref())?
+                        .ok_or(PlatzClientError::NoConfigFound)?;
+                let mut provider = ConfigFileProvider::new().with_config(config);
+                if let Some(config_path) = self.config_path {
+                    provider = provider.config_path(config_path);
+                }
+                if let Some(profile) = self.profile {
+                    provider = provider.profile(profile);
+                }
+                Box::new(provider)
+            }
+            None => Box::new(ChainProvider::default_chain_with(
+                EnvProvider::new().strict(self.strict_env),
+            )),
+        };
+        let mut client = PlatzClient::from_provider(provider);
+        if let Some(refresh_skew) = self.refresh_skew {
+            client = client.with_refresh_skew(refresh_skew);
+        }
+        client.credentials().await?;
+        Ok(client)
+    }
+}
@@ -1,27 +1,41 @@
+use super::config_file::{
+    ConfigLayers, ConfigLock, Credentials, ProfileInfo, TomlConfig, PROJECT_CONFIG_FILE_NAME,
+};
 use super::error::PlatzClientError;
-use async_std::fs::read_to_string;
+use super::login::refresh_access_token;
+use super::provider::ProvidedCredentials;
+use super::source::{ConfigAttempt, ConfigExplanation, ConfigOutcome, CredentialSource};
+use super::token_command::{TokenCommand, TokenCommandOutput, DEFAULT_TOKEN_COMMAND_TIMEOUT};
+use crate::SecretString;
+use async_std::fs::{metadata, read_to_string};
 use chrono::prelude::*;
 use futures::future::try_join3;
 use reqwest::header::{HeaderName, HeaderValue, AUTHORIZATION};
 use serde::Deserialize;
-use std::{env::var_os, ffi::OsString, io::ErrorKind, path::PathBuf};
+use std::{
+    env::var_os,
+    ffi::OsString,
+    io::ErrorKind,
+    path::Path,
+    sync::Once,
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::warn;
 use url::Url;
 
-fn always_false() -> bool {
-    false
-}
-
-#[derive(Deserialize, Clone, Debug)]
-struct ProfileInfo {
-    url: String,
-    #[serde(flatten)]
-    credentials: Credentials,
-    #[serde(default = "always_false")]
-    default_profile: bool,
-}
+pub(super) const SECRET_DIR: &str = "/var/run/secrets/platz";
+const SECRET_CHECK_INTERVAL: Duration = Duration::from_secs(10);
 
 impl ProfileInfo {
-    pub fn to_client(&self) -> Result<PlatzClientConfig, PlatzClientError> {
+    pub(super) fn to_client(
+        &self,
+        conf_path: &Path,
+        name: &str,
+    ) -> Result<PlatzClientConfig, PlatzClientError> {
+        let source = CredentialSource::ConfigFile {
+            path: conf_path.to_owned(),
+            profile: name.to_owned(),
+        };
         let server_url = self
             .url
             .parse()
@@ -31,57 +45,47 @@ impl ProfileInfo {
             Credentials::AccessToken {
                 access_token,
                 expired_at,
+                ..
             } => PlatzClientConfig {
                 server_url,
                 scheme: AuthScheme::Bearer,
                 contents: access_token.clone(),
                 expires_at: *expired_at,
+                source,
+                secret_poll: None,
+                token_command: None,
             },
             Credentials::UserToken { user_token } => PlatzClientConfig {
                 server_url,
                 scheme: AuthScheme::XPlatzToken,
                 contents: user_token.clone(),
                 expires_at: None,
+                source,
+                secret_poll: None,
+                token_command: None,
+            },
+            // The command runs on first use, through `refresh`.
+            Credentials::TokenCommand {
+                token_command,
+                token_command_timeout_secs,
+            } => PlatzClientConfig {
+                server_url,
+                scheme: AuthScheme::Bearer,
+                contents: SecretString::default(),
+                expires_at: Some(DateTime::UNIX_EPOCH),
+                source,
+                secret_poll: None,
+                token_command: Some(TokenCommand {
+                    command: token_command.clone(),
+                    timeout: token_command_timeout_secs
+                        .map_or(DEFAULT_TOKEN_COMMAND_TIMEOUT, Duration::from_secs),
+                }),
             },
         })
     }
 }
 
-#[derive(Deserialize, Clone, Debug)]
-#[serde(untagged)]
-enum Credentials {
-    AccessToken {
-        access_token: String,
-        expired_at: Option<DateTime<Utc>>,
-    },
-    UserToken {
-        user_token: String,
-    },
-}
-
-#[derive(Debug, Deserialize)]
-struct TomlConfig {
-    profile: std::collections::HashMap<String, ProfileInfo>,
-}
-
-impl TomlConfig {
-    pub fn get_default_profile(&self) -> Result<Option<&ProfileInfo>, PlatzClientError> {
-        let mut default_profile: Option<&ProfileInfo> = None;
-        for profile_info in self.profile.values() {
-            if profile_info.default_profile {
-                if default_profile.is_some() {
-                    return Err(PlatzClientError::ConfigTomlError(
-                        "Multiple default profiles",
-                    ));
-                }
-                default_profile = Some(profile_info)
-            }
-        }
-        Ok(default_profile)
-    }
-}
-
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
 enum AuthScheme {
     Bearer,
     XPlatzToken,
@@ -90,8 +94,36 @@ enum AuthScheme {
 pub(super) struct PlatzClientConfig {
     pub server_url: Url,
     scheme: AuthScheme,
-    contents: String,
+    contents: SecretString,
     expires_at: Option<DateTime<Utc>>,
+    source: CredentialSource,
+    secret_poll: Option<SecretPoll>,
+    token_command: Option<TokenCommand>,
+}
+
+/// State for polling a mounted secret for rotation.
+struct SecretPoll {
+    modified: Option<SystemTime>,
+    checked_at: Instant,
+}
+
+async fn secret_modified(dir: &Path) -> Option<SystemTime> {
+    metadata(dir.join("access_token"))
+        .await
+        .ok()?
+        .modified()
+        .ok()
+}
+
+fn config_outcome(
+    result: Result<Option<PlatzClientConfig>, PlatzClientError>,
+    skip_reason: impl FnOnce() -> String,
+) -> ConfigOutcome {
+    match result {
+        Ok(Some(config)) => ConfigOutcome::Shadowed(config.source),
+        Ok(None) => ConfigOutcome::Skipped(skip_reason()),
+        Err(err) => ConfigOutcome::Failed(err.to_string()),
+    }
 }
 
 fn get_env_var_or_value(env_var_name: &'static str) -> Result<Option<String>, PlatzClientError> {
@@ -100,49 +132,161 @@ fn get_env_var_or_value(env_var_name: &'static str) -> Result<Option<String>, Pl
         .transpose()
         .map_err(move |_| PlatzClientError::EnvVarParseError(env_var_name))
 }
-impl PlatzClientConfig {
-    /// Create a new PlatzClient by trying new_from_env, then
-    /// new_from_secret, whichever succeeds first.
-    /// If no token can be found, a PlatzClientError::NotFound is returned.
-    pub async fn new() -> Result<Self, PlatzClientError> {
-        if let Some(config) = Self::new_from_env()? {
-            Ok(config)
-        } else if let Some(config) = Self::new_from_configuration(None).await? {
-            Ok(config)
-        } else if let Some(config) = Self::new_from_secret().await? {
-            Ok(config)
-        } else {
-            Err(PlatzClientError::NoConfigFound)
+
+const ENV_URL_VAR: &str = "PLATZ_URL";
+const ENV_ACCESS_TOKEN_VAR: &str = "PLATZ_ACCESS_TOKEN";
+const ENV_EXPIRES_AT_VAR: &str = "PLATZ_TOKEN_EXPIRES_AT";
+const ENV_USER_TOKEN_VAR: &str = "PLATZ_USER_TOKEN";
+
+static USER_TOKEN_DEPRECATION: Once = Once::new();
+
+/// Environment variables holding a token, and how each token is sent:
+///
+/// | Variable             | Header                   | Same as in `config.toml` |
+/// |----------------------|--------------------------|--------------------------|
+/// | `PLATZ_API_TOKEN`    | `x-platz-token`          | `user_token`             |
+/// | `PLATZ_USER_TOKEN`   | `Authorization: Bearer`  | `access_token`           |
+/// | `PLATZ_ACCESS_TOKEN` | `Authorization: Bearer`  | `access_token`           |
+///
+/// `PLATZ_USER_TOKEN` is deprecated and logs a warning. It's sent as a
+/// bearer token as in earlier versions, use `PLATZ_ACCESS_TOKEN` instead.
+///
+/// `PLATZ_TOKEN_EXPIRES_AT` optionally holds the RFC 3339 expiry time of
+/// `PLATZ_ACCESS_TOKEN`. If several token variables are set, the first one
+/// in this list is used.
+const ENV_TOKEN_VARS: [(&str, AuthScheme); 3] = [
+    ("PLATZ_API_TOKEN", AuthScheme::XPlatzToken),
+    (ENV_USER_TOKEN_VAR, AuthScheme::Bearer),
+    (ENV_ACCESS_TOKEN_VAR, AuthScheme::Bearer),
+];
+
+/// The Platz environment variables that are set.
+struct EnvVars {
+    server_url: Option<Url>,
+    tokens: Vec<(&'static str, AuthScheme, SecretString)>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+impl EnvVars {
+    fn read() -> Result<Self, PlatzClientError> {
+        Self::read_with(get_env_var_or_value)
+    }
+
+    /// Same as `read`, getting variables with `get_var`.
+    fn read_with<F>(get_var: F) -> Result<Self, PlatzClientError>
+    where
+        F: Fn(&'static str) -> Result<Option<String>, PlatzClientError>,
+    {
+        let server_url = get_var(ENV_URL_VAR)?
+            .map(|str| Url::parse(&str))
+            .transpose()
+            .map_err(|_| PlatzClientError::EnvVarParseError(ENV_URL_VAR))?;
+        let mut tokens = Vec::new();
+        for (token_var, scheme) in ENV_TOKEN_VARS {
+            if let Some(token) = get_var(token_var)? {
+                tokens.push((token_var, scheme, token.into()));
+            }
         }
+        let expires_at = get_var(ENV_EXPIRES_AT_VAR)?
+            .map(|str| DateTime::parse_from_rfc3339(&str))
+            .transpose()
+            .map_err(|_| PlatzClientError::EnvVarParseError(ENV_EXPIRES_AT_VAR))?
+            .map(|expires_at| expires_at.with_timezone(&Utc));
+        Ok(Self {
+            server_url,
+            tokens,
+            expires_at,
+        })
+    }
+
+    fn token_var_names() -> String {
+        ENV_TOKEN_VARS
+            .iter()
+            .map(|(token_var, _)| *token_var)
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    /// Try creating PlatzClient from PLATZ_URL and PLATZ_API_TOKEN environment
-    /// variables. If at least one of the variables is not defined, None is returned.
+    /// Describes a partial or ambiguous configuration, which strict mode
+    /// rejects.
+    fn problem(&self) -> Option<String> {
+        let token_vars: Vec<_> = self
+            .tokens
+            .iter()
+            .map(|(token_var, ..)| *token_var)
+            .collect();
+        if token_vars.len() > 1 {
+            return Some(format!(
+                "only one of {} may be set, but {} are set",
+                Self::token_var_names(),
+                token_vars.join(" and ")
+            ));
+        }
+        match (&self.server_url, token_vars.first()) {
+            (Some(_), None) => {
+                return Some(format!(
+                    "{ENV_URL_VAR} is set, but none of {} is set",
+                    Self::token_var_names()
+                ));
+            }
+            (None, Some(token_var)) => {
+                return Some(format!("{token_var} is set, but {ENV_URL_VAR} isn't"));
+            }
+            _ => (),
+        }
+        if self.expires_at.is_some() && token_vars.first() != Some(&ENV_ACCESS_TOKEN_VAR) {
+            return Some(format!(
+                "{ENV_EXPIRES_AT_VAR} is set, but {ENV_ACCESS_TOKEN_VAR} isn't"
+            ));
+        }
+        None
+    }
+}
+impl PlatzClientConfig {
+    /// Try creating PlatzClient from the `PLATZ_URL` environment variable and
+    /// one of the token variables in `ENV_TOKEN_VARS`. If the URL or token is
+    /// missing, None is returned, or an error in `strict` mode.
     /// If the variables exist and there's an error parsing them, this error is
     /// returned and no further configuration would be loaded.
-    pub fn new_from_env() -> Result<Option<Self>, PlatzClientError> {
-        let server_url: Option<Url> = get_env_var_or_value("PLATZ_URL")?
-            .map(|str| Url::parse(&str))
-            .transpose()
-            .map_err(|_| PlatzClientError::EnvVarParseError("PLATZ_URL"))?;
-        let (scheme, contents) = if let Some(api_token) = get_env_var_or_value("PLATZ_API_TOKEN")? {
-            (AuthScheme::XPlatzToken, Some(api_token))
-        } else {
-            (
-                AuthScheme::Bearer,
-                get_env_var_or_value("PLATZ_USER_TOKEN")?,
-            )
+    pub fn new_from_env(strict: bool) -> Result<Option<Self>, PlatzClientError> {
+        Self::from_env_vars(EnvVars::read()?, strict)
+    }
+
+    fn from_env_vars(env_vars: EnvVars, strict: bool) -> Result<Option<Self>, PlatzClientError> {
+        if strict && let Some(problem) = env_vars.problem() {
+            return Err(PlatzClientError::EnvConfigError(problem));
+        }
+        let EnvVars {
+            server_url: Some(server_url),
+            tokens,
+            expires_at,
+        } = env_vars
+        else {
+            return Ok(None);
         };
-        match (server_url, contents) {
-            (Some(server_url), Some(contents)) => Ok(Self {
-                server_url,
-                scheme,
-                contents,
-                expires_at: None,
-            }
-            .into()),
-            _ => Ok(None),
+        let Some((token_var, scheme, contents)) = tokens.into_iter().next() else {
+            return Ok(None);
+        };
+        if token_var == ENV_USER_TOKEN_VAR {
+            USER_TOKEN_DEPRECATION.call_once(|| {
+                warn!(
+                    "{ENV_USER_TOKEN_VAR} is deprecated, set {ENV_ACCESS_TOKEN_VAR} for bearer \
+                     tokens or PLATZ_API_TOKEN for user tokens"
+                )
+            });
         }
+        Ok(Some(Self {
+            server_url,
+            scheme,
+            contents,
+            expires_at: expires_at.filter(|_| token_var == ENV_ACCESS_TOKEN_VAR),
+            source: CredentialSource::Env {
+                url_var: ENV_URL_VAR,
+                token_var,
+            },
+            secret_poll: None,
+            token_command: None,
+        }))
     }
 
     /// Try creating PlatzClient from files on disk. This works
@@ -150,10 +294,15 @@ impl PlatzClientConfig {
     /// under `/var/run/secrets/platz`. The secret is rotated
     /// regularly by Platz.
     pub async fn new_from_secret() -> Result<Option<Self>, PlatzClientError> {
+        Self::new_from_secret_dir(Path::new(SECRET_DIR)).await
+    }
+
+    /// Same as `new_from_secret`, reading the secret from another directory.
+    pub async fn new_from_secret_dir(dir: &Path) -> Result<Option<Self>, PlatzClientError> {
         match try_join3(
-            read_to_string("/var/run/secrets/platz/access_token"),
-            read_to_string("/var/run/secrets/platz/server_url"),
-            read_to_string("/var/run/secrets/platz/expires_at"),
+            read_to_string(dir.join("access_token")),
+            read_to_string(dir.join("server_url")),
+            read_to_string(dir.join("expires_at")),
         )
         .await
         {
@@ -162,12 +311,20 @@ impl PlatzClientConfig {
                     .parse()
                     .map_err(PlatzClientError::MountedUrlParseError)?,
                 scheme: AuthScheme::Bearer,
-                contents: access_token,
+                contents: access_token.into(),
                 expires_at: Some(
                     expires_at
                         .parse()
                         .map_err(PlatzClientError::MountedExpiryParseError)?,
                 ),
+                source: CredentialSource::MountedSecret {
+                    dir: dir.to_owned(),
+                },
+                secret_poll: Some(SecretPoll {
+                    modified: secret_modified(dir).await,
+                    checked_at: Instant::now(),
+                }),
+                token_command: None,
             })),
             Err(err) => match err.kind() {
                 ErrorKind::NotFound => Ok(None),
@@ -177,92 +334,485 @@ impl PlatzClientConfig {
     }
 
     async fn from_config_toml(
-        base_path: Option<PathBuf>,
+        conf_path: &Path,
         profile_name: &Option<String>,
     ) -> Result<Option<Self>, PlatzClientError> {
-        if base_path.is_none() {
+        let Some(toml_conf) = TomlConfig::load(conf_path).await? else {
+            return Ok(None);
+        };
+        let name = toml_conf.selected_profile_name(profile_name.as_deref())?;
+        let profile_info = toml_conf.profile.get(name.as_str()).ok_or_else(|| {
+            PlatzClientError::ConfigTomlError("Requested profile does not exist in configuration")
+        })?;
+
+        Ok(Some(profile_info.to_client(conf_path, &name)?))
+    }
+
+    /// Create a config from a profile in layered config files. Returns `None`
+    /// if there are no config files.
+    pub(super) fn from_config_layers(
+        layers: &ConfigLayers,
+        profile_name: Option<&str>,
+    ) -> Result<Option<Self>, PlatzClientError> {
+        if layers.is_empty() {
             return Ok(None);
         }
-        let mut conf_path = base_path.unwrap();
-        conf_path.push("platz");
-        conf_path.push("config.toml");
-
-        match read_to_string(conf_path).await {
-            Ok(toml_data) => {
-                let toml_conf: TomlConfig = toml::from_str(&toml_data)
-                    .map_err(PlatzClientError::ConfigDeserializationError)?;
-                let default_profile = toml_conf.get_default_profile()?;
-                let requested_profile: Option<String> = if profile_name.is_some() {
-                    profile_name.clone()
-                } else {
-                    var_os("PLATZ_PROFILE")
-                        .map(OsString::into_string)
-                        .transpose()
-                        .map_err(|_| PlatzClientError::EnvVarParseError("PLATZ_PROFILE"))?
-                };
-                let profile_info = if let Some(name) = requested_profile {
-                    toml_conf.profile.get(name.as_str()).ok_or_else(|| {
-                        PlatzClientError::ConfigTomlError(
-                            "Requested profile does not exist in configuration",
-                        )
-                    })?
-                } else {
-                    default_profile.ok_or_else(|| {
-                        PlatzClientError::ConfigTomlError("Not default profile configured")
-                    })?
-                };
-
-                Ok(Some(profile_info.to_client()?))
+        let merged = layers.merged();
+        let name = merged.selected_profile_name(profile_name)?;
+        match (merged.profile.get(&name), layers.profile_path(&name)) {
+            (Some(profile_info), Some(conf_path)) => {
+                Ok(Some(profile_info.to_client(conf_path, &name)?))
             }
-            Err(err) => match err.kind() {
-                ErrorKind::NotFound => Ok(None),
-                kind => Err(PlatzClientError::ConfigReadError(kind)),
-            },
+            _ => Err(PlatzClientError::ConfigTomlError(
+                "Requested profile does not exist in configuration",
+            )),
         }
     }
 
     // Try creating PlatzClient from configuration files. This is the recommended
-    // way for CLI tools.
+    // way for CLI tools. See `ConfigLayers::load` for the files used.
     pub async fn new_from_configuration(
         server_name: Option<String>,
     ) -> Result<Option<Self>, PlatzClientError> {
-        let Some(home_dir) = dirs::home_dir() else {
-            return Ok(None);
+        Self::from_config_layers(&ConfigLayers::load(None).await?, server_name.as_deref())
+    }
+
+    /// Try every credential source in the same order as
+    /// `ChainProvider::default_chain_with` an `EnvProvider` using `strict_env`,
+    /// and report which one would be selected and why the others weren't.
+    pub async fn explain(strict_env: bool) -> ConfigExplanation {
+        let mut attempts = vec![ConfigAttempt {
+            description: "Environment variables".to_owned(),
+            outcome: config_outcome(Self::new_from_env(strict_env), || {
+                EnvVars::read()
+                    .ok()
+                    .and_then(|env_vars| env_vars.problem())
+                    .unwrap_or_else(|| format!("{ENV_URL_VAR} is not set"))
+            }),
+        }];
+        attempts.push(ConfigAttempt {
+            description: "Configuration files".to_owned(),
+            outcome: config_outcome(Self::new_from_configuration(None).await, || {
+                let search_paths: Vec<_> = TomlConfig::search_paths()
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                format!(
+                    "PLATZ_CONFIG is not set, no {PROJECT_CONFIG_FILE_NAME} was found and none of {} exist",
+                    search_paths.join(", ")
+                )
+            }),
+        });
+        attempts.push(ConfigAttempt {
+            description: format!("Mounted secret {SECRET_DIR}"),
+            outcome: config_outcome(Self::new_from_secret().await, || {
+                "access_token, server_url or expires_at is missing".to_owned()
+            }),
+        });
+
+        // Mirror `ChainProvider`, which stops at the first source that either loads
+        // or fails.
+        if let Some(attempt) = attempts.iter_mut().find(|attempt| {
+            matches!(
+                attempt.outcome,
+                ConfigOutcome::Shadowed(_) | ConfigOutcome::Failed(_)
+            )
+        }) && let ConfigOutcome::Shadowed(source) = &attempt.outcome
+        {
+            attempt.outcome = ConfigOutcome::Selected(source.clone());
+        }
+        ConfigExplanation { attempts }
+    }
+
+    /// Report whether a profile can be loaded from the config files, with
+    /// the same `config_path` and `profile_name` as `PlatzClientBuilder`.
+    pub(super) async fn explain_config_layers(
+        config_path: Option<&Path>,
+        profile_name: Option<&str>,
+    ) -> ConfigExplanation {
+        let description = match config_path {
+            Some(config_path) => format!("Configuration file {}", config_path.display()),
+            None => "Configuration files".to_owned(),
         };
-        let mut conf_path = home_dir;
-        conf_path.push(".config");
-        let config_data = Self::from_config_toml(Some(conf_path), &server_name).await?;
-        if config_data.is_some() {
-            Ok(config_data)
-        } else {
-            Self::from_config_toml(dirs::config_dir(), &server_name).await
+        let result = match ConfigLayers::load(config_path).await {
+            Ok(layers) => Self::from_config_layers(&layers, profile_name),
+            Err(err) => Err(err),
+        };
+        let outcome = match config_outcome(result, || "no config file was found".to_owned()) {
+            ConfigOutcome::Shadowed(source) => ConfigOutcome::Selected(source),
+            outcome => outcome,
+        };
+        ConfigExplanation {
+            attempts: vec![ConfigAttempt {
+                description,
+                outcome,
+            }],
         }
     }
 
     /// Checks that the current credentials haven't expired
     pub fn expired(&self) -> bool {
+        self.expires_within(chrono::Duration::zero())
+    }
+
+    /// Checks whether the current credentials expire within `skew` from now
+    pub fn expires_within(&self, skew: chrono::Duration) -> bool {
         if let Some(expires_at) = self.expires_at {
-            expires_at <= Utc::now()
+            expires_at <= Utc::now() + skew
         } else {
             false
         }
     }
 
-    /// Returns Authorization header content, possibly refreshing the current
-    /// credentials.
-    pub async fn get_authorization(&self) -> Result<(HeaderName, HeaderValue), PlatzClientError> {
-        match self.scheme {
-            AuthScheme::Bearer => Ok((
+    /// Checks whether both configs hold the same token
+    pub fn same_credentials(&self, other: &Self) -> bool {
+        self.contents == other.contents
+    }
+
+    /// Checks whether a mounted secret has been rotated since it was
+    /// loaded or last found changed. The secret's modification time is
+    /// checked at most every 10 seconds, and each change is only reported
+    /// once, even if reloading the secret doesn't yield a new token.
+    pub async fn secret_changed(&mut self) -> bool {
+        let (CredentialSource::MountedSecret { dir }, Some(secret_poll)) =
+            (&self.source, self.secret_poll.as_mut())
+        else {
+            return false;
+        };
+        if secret_poll.checked_at.elapsed() < SECRET_CHECK_INTERVAL {
+            return false;
+        }
+        secret_poll.checked_at = Instant::now();
+        let modified = secret_modified(dir).await;
+        if modified == secret_poll.modified {
+            return false;
+        }
+        secret_poll.modified = modified;
+        true
+    }
+
+    /// Re-read the credentials from the mounted secret or config file
+    /// profile they were loaded from. Returns `None` for other sources.
+    pub async fn reload(&self) -> Result<Option<Self>, PlatzClientError> {
+        match &self.source {
+            CredentialSource::MountedSecret { dir } => Self::new_from_secret_dir(dir).await,
+            // Only the profile's own file is read here, so whether it's a
+            // project `.platz.toml` isn't known. A profile can't turn into a
+            // `token_command` on reload, so a project file edited after the
+            // client was created can't make it run one.
+            CredentialSource::ConfigFile { path, profile } => {
+                Ok(Self::from_config_toml(path, &Some(profile.clone()))
+                    .await?
+                    .filter(|reloaded| {
+                        reloaded.token_command.is_none() || self.token_command.is_some()
+                    }))
+            }
+            CredentialSource::Env { .. } | CredentialSource::Provider { .. } => Ok(None),
+        }
+    }
+
+    /// Get new credentials before the current ones expire. Mounted secrets
+    /// are re-read, since Platz rotates them ahead of expiry. Profiles with
+    /// a `token_command` run the command again. Access tokens
+    /// from a config file profile with a refresh token are refreshed and
+    /// written back to the profile. The config file is locked while
+    /// refreshing, and if another process has already refreshed the token,
+    /// its token is used instead of refreshing again.
+    /// Returns `None` if the credentials can't be refreshed.
+    pub async fn refresh(&self, skew: chrono::Duration) -> Result<Option<Self>, PlatzClientError> {
+        if let Some(token_command) = &self.token_command {
+            let (scheme, contents, expires_at) = match token_command.run().await? {
+                TokenCommandOutput::AccessToken {
+                    access_token,
+                    expires_at,
+                } => (AuthScheme::Bearer, access_token, expires_at),
+                TokenCommandOutput::UserToken { user_token } => {
+                    (AuthScheme::XPlatzToken, user_token, None)
+                }
+            };
+            return Ok(Some(Self {
+                server_url: self.server_url.clone(),
+                scheme,
+                contents,
+                expires_at,
+                source: self.source.clone(),
+                secret_poll: None,
+                token_command: Some(token_command.clone()),
+            }));
+        }
+        let (conf_path, profile_name) = match &self.source {
+            CredentialSource::MountedSecret { .. } => {
+                return Ok(self
+                    .reload()
+                    .await?
+                    .filter(|reloaded| !reloaded.same_credentials(self)));
+            }
+            CredentialSource::ConfigFile { path, profile } => (path, profile),
+            CredentialSource::Env { .. } | CredentialSource::Provider { .. } => return Ok(None),
+        };
+        let _lock = ConfigLock::acquire(conf_path).await?;
+        let Some(mut toml_conf) = TomlConfig::load(conf_path).await? else {
+            return Ok(None);
+        };
+        let Some(profile_info) = toml_conf.profile.get_mut(profile_name) else {
+            return Ok(None);
+        };
+        let Credentials::AccessToken {
+            access_token,
+            expired_at,
+            refresh,
+        } = &mut profile_info.credentials
+        else {
+            return Ok(None);
+        };
+
+        let refreshed_elsewhere = *access_token != self.contents
+            && expired_at.is_none_or(|expires_at| expires_at > Utc::now() + skew);
+        if !refreshed_elsewhere {
+            let Some(refresh_info) = refresh.as_ref() else {
+                return Ok(None);
+            };
+            let token = refresh_access_token(refresh_info).await?;
+            *access_token = token.access_token;
+            *expired_at = token.expires_at;
+            *refresh = token.refresh;
+        }
+
+        let config = profile_info.to_client(conf_path, profile_name)?;
+        if !refreshed_elsewhere {
+            toml_conf.save(conf_path).await?;
+        }
+        Ok(Some(config))
+    }
+
+    /// Returns the server URL and authorization header for the current
+    /// credentials. The header value is marked as sensitive so it's redacted
+    /// in `Debug` output.
+    pub fn credentials(&self) -> Result<ProvidedCredentials, PlatzClientError> {
+        let (header_name, value) = match self.scheme {
+            AuthScheme::Bearer => (
                 AUTHORIZATION,
-                HeaderValue::try_from(format!("Bearer {}", self.contents))
-                    .map_err(|_| PlatzClientError::ErrorCreatingAuthHeader)?,
-            )),
-            AuthScheme::XPlatzToken => Ok((
+                SecretString::new(format!("Bearer {}", self.contents.expose_secret())),
+            ),
+            AuthScheme::XPlatzToken => (
                 HeaderName::try_from("x-platz-token")
                     .map_err(|_| PlatzClientError::ErrorCreatingAuthHeader)?,
-                HeaderValue::try_from(self.contents.clone())
-                    .map_err(|_| PlatzClientError::ErrorCreatingAuthHeader)?,
-            )),
+                self.contents.clone(),
+            ),
+        };
+        let mut header_value = HeaderValue::try_from(value.expose_secret())
+            .map_err(|_| PlatzClientError::ErrorCreatingAuthHeader)?;
+        header_value.set_sensitive(true);
+        Ok(ProvidedCredentials {
+            server_url: self.server_url.clone(),
+            header_name,
+            header_value,
+            expires_at: self.expires_at,
+            source: self.source.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const URL: (&str, &str) = ("PLATZ_URL", "https://platz.example.com");
+
+    fn env_vars(vars: &[(&'static str, &str)]) -> Result<EnvVars, PlatzClientError> {
+        let vars: HashMap<&str, String> = vars
+            .iter()
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect();
+        EnvVars::read_with(|name| Ok(vars.get(name).cloned()))
+    }
+
+    fn from_env(
+        vars: &[(&'static str, &str)],
+        strict: bool,
+    ) -> Result<Option<PlatzClientConfig>, PlatzClientError> {
+        PlatzClientConfig::from_env_vars(env_vars(vars)?, strict)
+    }
+
+    #[test]
+    fn maps_each_token_variable() {
+        for (token_var, scheme) in [
+            ("PLATZ_API_TOKEN", AuthScheme::XPlatzToken),
+            ("PLATZ_USER_TOKEN", AuthScheme::Bearer),
+            ("PLATZ_ACCESS_TOKEN", AuthScheme::Bearer),
+        ] {
+            for strict in [false, true] {
+                let config = from_env(&[URL, (token_var, "token")], strict)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(config.scheme, scheme, "{token_var}");
+                assert_eq!(config.contents.expose_secret(), "token");
+                assert_eq!(config.server_url.as_str(), "https://platz.example.com/");
+                assert!(matches!(
+                    config.source,
+                    CredentialSource::Env { token_var: var, .. } if var == token_var
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_expiry_of_access_token() {
+        let config = from_env(
+            &[
+                URL,
+                ("PLATZ_ACCESS_TOKEN", "token"),
+                ("PLATZ_TOKEN_EXPIRES_AT", "2030-01-01T00:00:00Z"),
+            ],
+            true,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            config.expires_at,
+            Some("2030-01-01T00:00:00Z".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn rejects_unparsable_variables() {
+        assert!(matches!(
+            env_vars(&[("PLATZ_URL", "not a url")]),
+            Err(PlatzClientError::EnvVarParseError("PLATZ_URL"))
+        ));
+        assert!(matches!(
+            env_vars(&[URL, ("PLATZ_TOKEN_EXPIRES_AT", "tomorrow")]),
+            Err(PlatzClientError::EnvVarParseError("PLATZ_TOKEN_EXPIRES_AT"))
+        ));
+    }
+
+    #[test]
+    fn no_variables_is_not_a_problem() {
+        assert!(env_vars(&[]).unwrap().problem().is_none());
+        assert!(from_env(&[], true).unwrap().is_none());
+    }
+
+    #[test]
+    fn partial_configs() {
+        for vars in [
+            &[URL][..],
+            &[("PLATZ_API_TOKEN", "token")][..],
+            &[("PLATZ_ACCESS_TOKEN", "token")][..],
+        ] {
+            assert!(env_vars(vars).unwrap().problem().is_some(), "{vars:?}");
+            assert!(from_env(vars, false).unwrap().is_none(), "{vars:?}");
+            assert!(
+                matches!(
+                    from_env(vars, true),
+                    Err(PlatzClientError::EnvConfigError(_))
+                ),
+                "{vars:?}"
+            );
         }
     }
+
+    #[test]
+    fn multiple_token_variables() {
+        let vars = [
+            URL,
+            ("PLATZ_API_TOKEN", "api"),
+            ("PLATZ_ACCESS_TOKEN", "access"),
+        ];
+        let problem = env_vars(&vars).unwrap().problem().unwrap();
+        assert!(problem.contains("PLATZ_API_TOKEN and PLATZ_ACCESS_TOKEN"));
+        assert!(matches!(
+            from_env(&vars, true),
+            Err(PlatzClientError::EnvConfigError(_))
+        ));
+        // Without strict mode, the first variable in the list wins.
+        let config = from_env(&vars, false).unwrap().unwrap();
+        assert_eq!(config.scheme, AuthScheme::XPlatzToken);
+        assert_eq!(config.contents.expose_secret(), "api");
+    }
+
+    #[test]
+    fn expiry_without_access_token() {
+        let vars = [
+            URL,
+            ("PLATZ_API_TOKEN", "token"),
+            ("PLATZ_TOKEN_EXPIRES_AT", "2030-01-01T00:00:00Z"),
+        ];
+        assert!(env_vars(&vars)
+            .unwrap()
+            .problem()
+            .unwrap()
+            .contains("PLATZ_TOKEN_EXPIRES_AT"));
+        assert!(matches!(
+            from_env(&vars, true),
+            Err(PlatzClientError::EnvConfigError(_))
+        ));
+        // Without strict mode, the expiry is ignored.
+        let config = from_env(&vars, false).unwrap().unwrap();
+        assert_eq!(config.expires_at, None);
+    }
+
+    fn write_secret(dir: &Path, access_token: &str, modified: SystemTime) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("server_url"), "https://platz.example.com").unwrap();
+        std::fs::write(dir.join("expires_at"), "2999-01-01T00:00:00Z").unwrap();
+        let token_path = dir.join("access_token");
+        std::fs::write(&token_path, access_token).unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(token_path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    /// Pretend the secret was last checked long enough ago.
+    fn skip_check_interval(config: &mut PlatzClientConfig) {
+        let secret_poll = config.secret_poll.as_mut().unwrap();
+        secret_poll.checked_at = Instant::now()
+            .checked_sub(SECRET_CHECK_INTERVAL)
+            .unwrap_or(secret_poll.checked_at);
+    }
+
+    #[test]
+    fn picks_up_rotated_secret() {
+        let dir = std::env::temp_dir().join(format!(
+            "platz-sdk-secret-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let loaded_at = SystemTime::now() - Duration::from_secs(60);
+        write_secret(&dir, "old", loaded_at);
+        async_std::task::block_on(async {
+            let mut config = PlatzClientConfig::new_from_secret_dir(&dir)
+                .await
+                .unwrap()
+                .unwrap();
+            // Checks are rate limited.
+            assert!(!config.secret_changed().await);
+            skip_check_interval(&mut config);
+            assert!(!config.secret_changed().await);
+
+            write_secret(&dir, "new", loaded_at + Duration::from_secs(30));
+            assert!(!config.secret_changed().await);
+            skip_check_interval(&mut config);
+            assert!(config.secret_changed().await);
+            // A change is only reported once.
+            skip_check_interval(&mut config);
+            assert!(!config.secret_changed().await);
+
+            let refreshed = config
+                .refresh(chrono::Duration::minutes(1))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(refreshed.contents.expose_secret(), "new");
+            assert!(refreshed
+                .refresh(chrono::Duration::minutes(1))
+                .await
+                .unwrap()
+                .is_none());
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,713 @@
+use super::error::PlatzClientError;
+use crate::SecretString;
+use async_std::fs::{create_dir_all, read_to_string, remove_file, rename, OpenOptions};
+use async_std::io::WriteExt;
+use async_std::task::sleep;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env::var_os;
+use std::ffi::OsString;
+use std::fs::TryLockError;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// File name of per-project config files, searched for in the current
+/// directory and its ancestors.
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".platz.toml";
+
+fn always_false() -> bool {
+    false
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Write a file only its owner can read, since config files hold tokens.
+async fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use async_std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ProfileInfo {
+    pub url: String,
+    #[serde(flatten)]
+    pub credentials: Credentials,
+    #[serde(default = "always_false", skip_serializing_if = "is_false")]
+    pub default_profile: bool,
+}
+
+impl ProfileInfo {
+    pub fn has_token_command(&self) -> bool {
+        matches!(self.credentials, Credentials::TokenCommand { .. })
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum Credentials {
+    AccessToken {
+        access_token: SecretString,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expired_at: Option<DateTime<Utc>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        refresh: Option<RefreshInfo>,
+    },
+    UserToken {
+        user_token: SecretString,
+    },
+    /// A credential helper command printing a token as JSON, either
+    /// `{"access_token": "...", "expires_at": "..."}` or
+    /// `{"user_token": "..."}`. The command runs when the token is first
+    /// needed, and again when it's about to expire.
+    TokenCommand {
+        token_command: Vec<String>,
+        /// How long the command may run before it's killed, 30 seconds by
+        /// default.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_command_timeout_secs: Option<u64>,
+    },
+}
+
+/// What's needed to refresh an access token with the OAuth 2.0
+/// `refresh_token` grant.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct RefreshInfo {
+    pub refresh_token: SecretString,
+    pub token_url: String,
+    pub client_id: String,
+}
+
+/// The contents of a `platz/config.toml` or `.platz.toml` file, holding
+/// named profiles.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TomlConfig {
+    /// Profile to use instead of the default profile, unless `PLATZ_PROFILE`
+    /// is set. Lets a project's `.platz.toml` pin a profile defined in the
+    /// user's config file without copying its credentials.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_profile: Option<String>,
+    #[serde(default)]
+    pub profile: BTreeMap<String, ProfileInfo>,
+}
+
+impl TomlConfig {
+    /// The config file set by the `PLATZ_CONFIG` environment variable.
+    pub fn env_path() -> Result<Option<PathBuf>, PlatzClientError> {
+        Ok(var_os("PLATZ_CONFIG")
+            .map(OsString::into_string)
+            .transpose()
+            .map_err(|_| PlatzClientError::EnvVarParseError("PLATZ_CONFIG"))?
+            .map(PathBuf::from))
+    }
+
+    /// The nearest `.platz.toml` in the current directory or its ancestors.
+    pub fn find_project_path() -> Option<PathBuf> {
+        std::env::current_dir()
+            .ok()?
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Paths searched for `config.toml`, in order: `~/.config/platz` and
+    /// then the platform's config directory.
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut search_paths: Vec<PathBuf> = dirs::home_dir()
+            .map(|home_dir| home_dir.join(".config"))
+            .into_iter()
+            .chain(dirs::config_dir())
+            .map(|config_dir| config_dir.join("platz").join("config.toml"))
+            .collect();
+        search_paths.dedup();
+        search_paths
+    }
+
+    /// The user config file that would be read, or written when none exists
+    /// yet. This is `PLATZ_CONFIG` if set, otherwise the first existing file
+    /// from `search_paths`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(Some(env_path)) = Self::env_path() {
+            return Some(env_path);
+        }
+        let search_paths = Self::search_paths();
+        search_paths
+            .iter()
+            .find(|path| path.exists())
+            .or_else(|| search_paths.first())
+            .cloned()
+    }
+
+    /// Load a config file. Returns `None` if the file doesn't exist.
+    pub async fn load(path: &Path) -> Result<Option<Self>, PlatzClientError> {
+        match read_to_string(path).await {
+            Ok(toml_data) => Ok(Some(
+                toml::from_str(&toml_data).map_err(PlatzClientError::ConfigDeserializationError)?,
+            )),
+            Err(err) => match err.kind() {
+                ErrorKind::NotFound => Ok(None),
+                kind => Err(PlatzClientError::ConfigReadError(kind)),
+            },
+        }
+    }
+
+    /// Write the config file, creating its directory if needed. The file is
+    /// written to a temporary path and renamed, so readers never see a
+    /// partially written config.
+    pub async fn save(&self, path: &Path) -> Result<(), PlatzClientError> {
+        let toml_data =
+            toml::to_string_pretty(self).map_err(PlatzClientError::ConfigSerializationError)?;
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|err| PlatzClientError::ConfigWriteError(err.kind()))?;
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(format!(".{}.tmp", std::process::id()));
+        let tmp_path = PathBuf::from(tmp_path);
+        let result = match write_private(&tmp_path, toml_data.as_bytes()).await {
+            Ok(()) => rename(&tmp_path, path).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = result {
+            let _ = remove_file(&tmp_path).await;
+            return Err(PlatzClientError::ConfigWriteError(err.kind()));
+        }
+        Ok(())
+    }
+
+    /// The name of the profile to use: `requested` if given, otherwise
+    /// `PLATZ_PROFILE`, `use_profile` and finally the default profile.
+    pub fn selected_profile_name(
+        &self,
+        requested: Option<&str>,
+    ) -> Result<String, PlatzClientError> {
+        if let Some(name) = requested {
+            return Ok(name.to_owned());
+        }
+        if let Some(name) = var_os("PLATZ_PROFILE")
+            .map(OsString::into_string)
+            .transpose()
+            .map_err(|_| PlatzClientError::EnvVarParseError("PLATZ_PROFILE"))?
+        {
+            return Ok(name);
+        }
+        if let Some(name) = self.use_profile.as_ref() {
+            return Ok(name.clone());
+        }
+        Ok(self
+            .default_profile_name()?
+            .ok_or(PlatzClientError::ConfigTomlError(
+                "Not default profile configured",
+            ))?
+            .to_owned())
+    }
+
+    pub fn get_default_profile(&self) -> Result<Option<&ProfileInfo>, PlatzClientError> {
+        Ok(self
+            .default_profile_name()?
+            .and_then(|name| self.profile.get(name)))
+    }
+
+    pub fn default_profile_name(&self) -> Result<Option<&str>, PlatzClientError> {
+        let mut default_profile: Option<&str> = None;
+        for (name, profile_info) in self.profile.iter() {
+            if profile_info.default_profile {
+                if default_profile.is_some() {
+                    return Err(PlatzClientError::ConfigTomlError(
+                        "Multiple default profiles",
+                    ));
+                }
+                default_profile = Some(name)
+            }
+        }
+        Ok(default_profile)
+    }
+
+    pub fn add_profile<S>(&mut self, name: S, profile: ProfileInfo) -> Result<(), PlatzClientError>
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        if self.profile.contains_key(&name) {
+            return Err(PlatzClientError::ConfigTomlError("Profile already exists"));
+        }
+        let default_profile = profile.default_profile;
+        self.profile.insert(name.clone(), profile);
+        if default_profile {
+            self.set_default_profile(&name)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_profile(&mut self, name: &str) -> Result<ProfileInfo, PlatzClientError> {
+        self.profile
+            .remove(name)
+            .ok_or(PlatzClientError::ConfigTomlError("Profile does not exist"))
+    }
+
+    /// Rename a profile. Renaming a profile to its own name does nothing.
+    pub fn rename_profile<S>(&mut self, name: &str, new_name: S) -> Result<(), PlatzClientError>
+    where
+        S: Into<String>,
+    {
+        let new_name = new_name.into();
+        if new_name == name {
+            if !self.profile.contains_key(name) {
+                return Err(PlatzClientError::ConfigTomlError("Profile does not exist"));
+            }
+            return Ok(());
+        }
+        if self.profile.contains_key(&new_name) {
+            return Err(PlatzClientError::ConfigTomlError("Profile already exists"));
+        }
+        let profile = self.remove_profile(name)?;
+        self.profile.insert(new_name, profile);
+        Ok(())
+    }
+
+    /// Make `name` the only default profile.
+    pub fn set_default_profile(&mut self, name: &str) -> Result<(), PlatzClientError> {
+        if !self.profile.contains_key(name) {
+            return Err(PlatzClientError::ConfigTomlError("Profile does not exist"));
+        }
+        for (profile_name, profile_info) in self.profile.iter_mut() {
+            profile_info.default_profile = profile_name == name;
+        }
+        Ok(())
+    }
+}
+
+/// Config files layered on top of each other, from lowest to highest
+/// priority. A profile defined in a higher layer replaces the profile with
+/// the same name in lower layers, and a layer with a default profile or
+/// `use_profile` overrides the lower layers' choice.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    pub layers: Vec<(PathBuf, TomlConfig)>,
+}
+
+impl ConfigLayers {
+    /// Load the config files used by `PlatzClient`. If `explicit_path` or the
+    /// `PLATZ_CONFIG` environment variable is set, only that file is used
+    /// and it must exist. Otherwise the user config file from
+    /// `TomlConfig::search_paths` is used, overridden by the project's
+    /// `.platz.toml`, if any. Loading fails if the project's `.platz.toml`
+    /// would run a `token_command`.
+    pub async fn load(explicit_path: Option<&Path>) -> Result<Self, PlatzClientError> {
+        let explicit_path = match explicit_path {
+            Some(path) => Some(path.to_owned()),
+            None => TomlConfig::env_path()?,
+        };
+        if let Some(path) = explicit_path {
+            let toml_conf = TomlConfig::load(&path)
+                .await?
+                .ok_or(PlatzClientError::ConfigReadError(ErrorKind::NotFound))?;
+            return Ok(Self {
+                layers: vec![(path, toml_conf)],
+            });
+        }
+
+        let mut layers = Vec::new();
+        for path in TomlConfig::search_paths() {
+            if let Some(toml_conf) = TomlConfig::load(&path).await? {
+                layers.push((path, toml_conf));
+                break;
+            }
+        }
+        let mut config_layers = Self { layers };
+        if let Some(path) = TomlConfig::find_project_path()
+            && let Some(toml_conf) = TomlConfig::load(&path).await?
+        {
+            config_layers.push_project_layer(path, toml_conf)?;
+        }
+        Ok(config_layers)
+    }
+
+    /// Add a project's `.platz.toml` on top of the user config. It comes
+    /// with whatever repo the current directory is in, so it may not make
+    /// the client run a `token_command`, either by defining one or by
+    /// pinning a profile with one through `use_profile`.
+    fn push_project_layer(
+        &mut self,
+        path: PathBuf,
+        toml_conf: TomlConfig,
+    ) -> Result<(), PlatzClientError> {
+        if toml_conf
+            .profile
+            .values()
+            .any(ProfileInfo::has_token_command)
+        {
+            return Err(PlatzClientError::ConfigTomlError(
+                "A project .platz.toml can't define a token_command",
+            ));
+        }
+        if let Some(name) = toml_conf.use_profile.as_ref() {
+            let merged = self.merged();
+            if toml_conf
+                .profile
+                .get(name)
+                .or_else(|| merged.profile.get(name))
+                .is_some_and(ProfileInfo::has_token_command)
+            {
+                return Err(PlatzClientError::ConfigTomlError(
+                    "A project .platz.toml can't use a profile with a token_command",
+                ));
+            }
+        }
+        self.layers.push((path, toml_conf));
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// All layers merged into one config. Profiles in later layers replace
+    /// those with the same name in earlier layers. The default profile is
+    /// the one marked by the last layer marking one, so a profile redefined
+    /// in a layer that doesn't mark a default profile stays the default.
+    pub fn merged(&self) -> TomlConfig {
+        let mut merged = TomlConfig::default();
+        for (_, toml_conf) in self.layers.iter() {
+            let sets_default = toml_conf
+                .profile
+                .values()
+                .any(|profile_info| profile_info.default_profile);
+            if sets_default {
+                for profile_info in merged.profile.values_mut() {
+                    profile_info.default_profile = false;
+                }
+            }
+            if toml_conf.use_profile.is_some() {
+                merged.use_profile = toml_conf.use_profile.clone();
+            }
+            for (name, profile_info) in toml_conf.profile.iter() {
+                let was_default = merged
+                    .profile
+                    .get(name)
+                    .is_some_and(|profile_info| profile_info.default_profile);
+                merged.profile.insert(
+                    name.clone(),
+                    ProfileInfo {
+                        default_profile: profile_info.default_profile
+                            || (!sets_default && was_default),
+                        ..profile_info.clone()
+                    },
+                );
+            }
+        }
+        merged
+    }
+
+    /// The file a profile is read from, which is where refreshed tokens are
+    /// written back to.
+    pub fn profile_path(&self, profile_name: &str) -> Option<&Path> {
+        self.layers
+            .iter()
+            .rev()
+            .find(|(_, toml_conf)| toml_conf.profile.contains_key(profile_name))
+            .map(|(path, _)| path.as_path())
+    }
+}
+
+/// An exclusive lock on a config file, held while it's being read, modified
+/// and written back so concurrent processes don't overwrite each other's
+/// changes. This is an OS advisory lock on a `.lock` file next to the config
+/// file, so it's released when the lock is dropped or the process exits.
+/// The `.lock` file itself is left in place, since removing it would let
+/// two processes lock different files.
+pub struct ConfigLock {
+    _file: std::fs::File,
+}
+
+impl ConfigLock {
+    pub async fn acquire(conf_path: &Path) -> Result<Self, PlatzClientError> {
+        let mut lock_path = conf_path.as_os_str().to_owned();
+        lock_path.push(".lock");
+        let path = PathBuf::from(lock_path);
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)
+                .await
+                .map_err(|err| PlatzClientError::ConfigWriteError(err.kind()))?;
+        }
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|err| PlatzClientError::ConfigWriteError(err.kind()))?;
+
+        let started_at = Instant::now();
+        loop {
+            match file.try_lock() {
+                Ok(()) => return Ok(Self { _file: file }),
+                Err(TryLockError::WouldBlock) => {
+                    if started_at.elapsed() > LOCK_TIMEOUT {
+                        return Err(PlatzClientError::ConfigTomlError(
+                            "Timed out waiting for configuration file lock",
+                        ));
+                    }
+                    sleep(LOCK_RETRY_INTERVAL).await;
+                }
+                Err(TryLockError::Error(err)) => {
+                    return Err(PlatzClientError::ConfigWriteError(err.kind()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "platz-sdk-{name}-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn save_writes_private_file() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_dir("save");
+        let path = dir.join("config.toml");
+        async_std::task::block_on(TomlConfig::default().save(&path)).unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let leftovers: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(leftovers.len(), 1);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn save_removes_temp_file_on_failure() {
+        let dir = temp_dir("save-fail");
+        // Renaming a file over a non-empty directory fails.
+        let path = dir.join("config.toml");
+        std::fs::create_dir_all(path.join("occupied")).unwrap();
+        assert!(async_std::task::block_on(TomlConfig::default().save(&path)).is_err());
+        let entries: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, vec![OsString::from("config.toml")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = temp_dir("lock");
+        let path = dir.join("config.toml");
+        async_std::task::block_on(async {
+            let first = ConfigLock::acquire(&path).await.unwrap();
+            let waiting = async_std::task::spawn({
+                let path = path.clone();
+                async move { ConfigLock::acquire(&path).await.map(|_| Instant::now()) }
+            });
+            sleep(Duration::from_millis(300)).await;
+            let released_at = Instant::now();
+            drop(first);
+            let acquired_at = waiting.await.unwrap();
+            assert!(acquired_at >= released_at);
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn layers(layers: &[&str]) -> ConfigLayers {
+        ConfigLayers {
+            layers: layers
+                .iter()
+                .enumerate()
+                .map(|(index, toml_data)| {
+                    (
+                        PathBuf::from(format!("layer{index}.toml")),
+                        toml::from_str(toml_data).unwrap(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    const USER_CONFIG: &str = r#"
+[profile.prod]
+url = "https://prod.example.com"
+user_token = "prod"
+default_profile = true
+
+[profile.staging]
+url = "https://staging.example.com"
+user_token = "staging"
+"#;
+
+    #[test]
+    fn redefined_default_profile_stays_default() {
+        let layers = layers(&[
+            USER_CONFIG,
+            r#"
+[profile.prod]
+url = "https://prod.example.com"
+user_token = "project"
+"#,
+        ]);
+        let merged = layers.merged();
+        assert_eq!(merged.default_profile_name().unwrap(), Some("prod"));
+        assert!(!merged.profile["staging"].default_profile);
+        assert_eq!(layers.profile_path("prod"), Some(Path::new("layer1.toml")));
+    }
+
+    #[test]
+    fn later_layer_default_wins() {
+        let merged = layers(&[
+            USER_CONFIG,
+            r#"
+[profile.prod]
+url = "https://prod.example.com"
+user_token = "project"
+
+[profile.staging]
+url = "https://staging.example.com"
+user_token = "project"
+default_profile = true
+"#,
+        ])
+        .merged();
+        assert_eq!(merged.default_profile_name().unwrap(), Some("staging"));
+    }
+
+    const TOKEN_COMMAND_PROFILE: &str = r#"
+[profile.helper]
+url = "https://helper.example.com"
+token_command = ["platz-token"]
+"#;
+
+    #[test]
+    fn project_layer_cannot_define_token_command() {
+        let mut layers = layers(&[USER_CONFIG]);
+        let project = toml::from_str(TOKEN_COMMAND_PROFILE).unwrap();
+        assert!(layers
+            .push_project_layer(PathBuf::from(".platz.toml"), project)
+            .is_err());
+        assert_eq!(layers.layers.len(), 1);
+    }
+
+    #[test]
+    fn project_layer_cannot_use_token_command_profile() {
+        let mut layers = layers(&[&format!("{USER_CONFIG}{TOKEN_COMMAND_PROFILE}")]);
+        let project = toml::from_str(r#"use_profile = "helper""#).unwrap();
+        assert!(layers
+            .push_project_layer(PathBuf::from(".platz.toml"), project)
+            .is_err());
+
+        let project = toml::from_str(r#"use_profile = "staging""#).unwrap();
+        layers
+            .push_project_layer(PathBuf::from(".platz.toml"), project)
+            .unwrap();
+        assert_eq!(
+            layers.merged().selected_profile_name(None).unwrap(),
+            "staging"
+        );
+    }
+
+    fn profile_names(toml_conf: &TomlConfig) -> Vec<&str> {
+        toml_conf.profile.keys().map(String::as_str).collect()
+    }
+
+    fn user_token_profile(url: &str, default_profile: bool) -> ProfileInfo {
+        ProfileInfo {
+            url: url.to_owned(),
+            credentials: Credentials::UserToken {
+                user_token: "token".into(),
+            },
+            default_profile,
+        }
+    }
+
+    #[test]
+    fn adds_and_removes_profiles() {
+        let mut toml_conf: TomlConfig = toml::from_str(USER_CONFIG).unwrap();
+        assert!(toml_conf
+            .add_profile(
+                "prod",
+                user_token_profile("https://other.example.com", false)
+            )
+            .is_err());
+        toml_conf
+            .add_profile("dev", user_token_profile("https://dev.example.com", true))
+            .unwrap();
+        assert_eq!(profile_names(&toml_conf), vec!["dev", "prod", "staging"]);
+        // A new default profile replaces the previous one.
+        assert_eq!(toml_conf.default_profile_name().unwrap(), Some("dev"));
+
+        let removed = toml_conf.remove_profile("dev").unwrap();
+        assert_eq!(removed.url, "https://dev.example.com");
+        assert!(toml_conf.remove_profile("dev").is_err());
+        assert_eq!(toml_conf.default_profile_name().unwrap(), None);
+    }
+
+    #[test]
+    fn renames_profiles() {
+        let mut toml_conf: TomlConfig = toml::from_str(USER_CONFIG).unwrap();
+        toml_conf.rename_profile("prod", "production").unwrap();
+        assert_eq!(profile_names(&toml_conf), vec!["production", "staging"]);
+        assert_eq!(
+            toml_conf.default_profile_name().unwrap(),
+            Some("production")
+        );
+        toml_conf.rename_profile("staging", "staging").unwrap();
+        assert_eq!(profile_names(&toml_conf), vec!["production", "staging"]);
+        assert!(toml_conf.rename_profile("staging", "production").is_err());
+        assert!(toml_conf.rename_profile("missing", "missing").is_err());
+        assert!(toml_conf.rename_profile("missing", "other").is_err());
+    }
+
+    #[test]
+    fn sets_default_profile() {
+        let mut toml_conf: TomlConfig = toml::from_str(USER_CONFIG).unwrap();
+        toml_conf.set_default_profile("staging").unwrap();
+        assert_eq!(toml_conf.default_profile_name().unwrap(), Some("staging"));
+        assert!(!toml_conf.profile["prod"].default_profile);
+        assert!(toml_conf.set_default_profile("missing").is_err());
+        assert_eq!(toml_conf.default_profile_name().unwrap(), Some("staging"));
+    }
+
+    #[test]
+    fn saved_profiles_load_back() {
+        let dir = temp_dir("profiles");
+        let path = dir.join("config.toml");
+        let mut toml_conf: TomlConfig = toml::from_str(USER_CONFIG).unwrap();
+        toml_conf.rename_profile("staging", "stage").unwrap();
+        async_std::task::block_on(async {
+            toml_conf.save(&path).await.unwrap();
+            let loaded = TomlConfig::load(&path).await.unwrap().unwrap();
+            assert_eq!(profile_names(&loaded), vec!["prod", "stage"]);
+            assert_eq!(loaded.default_profile_name().unwrap(), Some("prod"));
+            assert!(TomlConfig::load(&dir.join("missing.toml"))
+                .await
+                .unwrap()
+                .is_none());
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,9 +1,10 @@
 #[derive(Debug, thiserror::Error)]
 pub enum PlatzClientError {
     #[error(r###"Could not find any Platz config. Use one of the following methods:
-    1. Set the PLATZ_URL and PLATZ_API_TOKEN environment variables.
+    1. Set the PLATZ_URL and PLATZ_API_TOKEN or PLATZ_ACCESS_TOKEN environment variables.
     2. Use "platz/config.toml" configuration file on your config directory.
-    2. When running from inside a Platz deployment, mount the "platz-creds" secret to /var/run/secrets/platz.json .
+    3. When running from inside a Platz deployment, mount the "platz-creds" secret to /var/run/secrets/platz .
+Use PlatzClient::explain_config() or PlatzClientBuilder::explain() to see why each of these wasn't used.
 "###)]
     NoConfigFound,
 
@@ -16,9 +17,18 @@ pub enum PlatzClientError {
     #[error("Error in configuration file: {0:?}")]
     ConfigTomlError(&'static str),
 
+    #[error("Error serializing configuration file: {0:?}")]
+    ConfigSerializationError(toml::ser::Error),
+
+    #[error("OS error while trying to write config: {0:?}")]
+    ConfigWriteError(std::io::ErrorKind),
+
     #[error("Error parsing {0} environment variable")]
     EnvVarParseError(&'static str),
 
+    #[error("Invalid environment configuration: {0}")]
+    EnvConfigError(String),
+
     #[error("Error parsing URL from mounted credentials: {0}")]
     MountedUrlParseError(url::ParseError),
 
@@ -34,6 +44,15 @@ pub enum PlatzClientError {
     #[error("HTTP Error: {0}")]
     HttpError(String),
 
+    #[error("Login failed: {0}")]
+    LoginError(String),
+
+    #[error("Error refreshing access token: {0}")]
+    TokenRefreshError(String),
+
+    #[error("Error running token command: {0}")]
+    TokenCommandError(String),
+
     #[error("Error creating authorization header")]
     ErrorCreatingAuthHeader,
 
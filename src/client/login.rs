@@ -0,0 +1,352 @@
+use super::config_file::{ConfigLock, Credentials, ProfileInfo, RefreshInfo, TomlConfig};
+use super::error::PlatzClientError;
+use crate::SecretString;
+use async_std::task::sleep;
+use chrono::prelude::*;
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use url::{form_urlencoded, Url};
+
+const DEVICE_CODE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const REFRESH_TOKEN_GRANT_TYPE: &str = "refresh_token";
+/// How long a request to the identity provider may take. Token refreshes
+/// hold the config file lock while waiting for it.
+const TOKEN_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// OAuth 2.0 device authorization grant (RFC 8628) against the identity
+/// provider used by the Platz server.
+#[derive(Debug, Clone)]
+pub struct DeviceCodeLogin {
+    pub device_authorization_url: Url,
+    pub token_url: Url,
+    pub client_id: String,
+    pub scope: Option<String>,
+}
+
+/// What the user has to do to complete a device code login. Show the
+/// `user_code` and ask the user to open `verification_uri` in a browser, or
+/// open `verification_uri_complete` directly when available.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCodePrompt {
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct DeviceAuthorizationResponse {
+    device_code: String,
+    #[serde(flatten)]
+    prompt: DeviceCodePrompt,
+    #[serde(default = "default_interval")]
+    interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: SecretString,
+    expires_in: Option<i64>,
+    refresh_token: Option<SecretString>,
+}
+
+impl TokenResponse {
+    fn into_login_token(self, token_url: &Url, client_id: &str) -> LoginToken {
+        LoginToken {
+            access_token: self.access_token,
+            expires_at: self
+                .expires_in
+                .map(|expires_in| Utc::now() + chrono::Duration::seconds(expires_in)),
+            refresh: self.refresh_token.map(|refresh_token| RefreshInfo {
+                refresh_token,
+                token_url: token_url.to_string(),
+                client_id: client_id.to_owned(),
+            }),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenErrorResponse {
+    error: String,
+    error_description: Option<String>,
+}
+
+/// An access token obtained by logging in.
+#[derive(Debug, Clone)]
+pub struct LoginToken {
+    pub access_token: SecretString,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub refresh: Option<RefreshInfo>,
+}
+
+async fn post_form(
+    url: &Url,
+    params: &[(&str, &str)],
+) -> Result<reqwest::Response, PlatzClientError> {
+    let body = form_urlencoded::Serializer::new(String::new())
+        .extend_pairs(params)
+        .finish();
+    Ok(reqwest::Client::new()
+        .post(url.clone())
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .timeout(TOKEN_REQUEST_TIMEOUT)
+        .body(body)
+        .send()
+        .await?)
+}
+
+/// Exchange a refresh token for a new access token. If the server rotates
+/// refresh tokens, the returned token holds the new refresh token,
+/// otherwise the current one is kept.
+pub async fn refresh_access_token(refresh: &RefreshInfo) -> Result<LoginToken, PlatzClientError> {
+    let token_url: Url = refresh
+        .token_url
+        .parse()
+        .map_err(|_| PlatzClientError::TokenRefreshError("Invalid token URL".to_owned()))?;
+    let response = post_form(
+        &token_url,
+        &[
+            ("grant_type", REFRESH_TOKEN_GRANT_TYPE),
+            ("refresh_token", refresh.refresh_token.expose_secret()),
+            ("client_id", refresh.client_id.as_str()),
+        ],
+    )
+    .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let reason = response
+            .json::<TokenErrorResponse>()
+            .await
+            .map(|error| error.error_description.unwrap_or(error.error))
+            .unwrap_or_else(|_| status.to_string());
+        return Err(PlatzClientError::TokenRefreshError(reason));
+    }
+    let mut token = response
+        .json::<TokenResponse>()
+        .await?
+        .into_login_token(&token_url, &refresh.client_id);
+    if token.refresh.is_none() {
+        token.refresh = Some(refresh.clone());
+    }
+    Ok(token)
+}
+
+impl DeviceCodeLogin {
+    /// Run the device code flow. `on_prompt` is called once with the code
+    /// the user has to enter, then the token endpoint is polled until the
+    /// user approves or denies the login, or the code expires.
+    pub async fn login<F>(&self, on_prompt: F) -> Result<LoginToken, PlatzClientError>
+    where
+        F: FnOnce(&DeviceCodePrompt),
+    {
+        let mut params = vec![("client_id", self.client_id.as_str())];
+        if let Some(scope) = self.scope.as_deref() {
+            params.push(("scope", scope));
+        }
+        let response = post_form(&self.device_authorization_url, &params).await?;
+        if !response.status().is_success() {
+            return Err(PlatzClientError::LoginError(format!(
+                "Device authorization failed with {}",
+                response.status()
+            )));
+        }
+        let authorization: DeviceAuthorizationResponse = response.json().await?;
+        on_prompt(&authorization.prompt);
+
+        let deadline =
+            Utc::now() + chrono::Duration::seconds(authorization.prompt.expires_in as i64);
+        let mut interval = authorization.interval;
+        loop {
+            sleep(Duration::from_secs(interval)).await;
+            if Utc::now() >= deadline {
+                return Err(PlatzClientError::LoginError(
+                    "Device code expired before login was completed".to_owned(),
+                ));
+            }
+
+            let response = post_form(
+                &self.token_url,
+                &[
+                    ("grant_type", DEVICE_CODE_GRANT_TYPE),
+                    ("device_code", authorization.device_code.as_str()),
+                    ("client_id", self.client_id.as_str()),
+                ],
+            )
+            .await?;
+
+            if response.status().is_success() {
+                let token: TokenResponse = response.json().await?;
+                return Ok(token.into_login_token(&self.token_url, &self.client_id));
+            }
+
+            let status = response.status();
+            let error: TokenErrorResponse = response.json().await.map_err(|_| {
+                PlatzClientError::LoginError(format!("Token request failed with {status}"))
+            })?;
+            match error.error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += 5,
+                _ => {
+                    return Err(PlatzClientError::LoginError(
+                        error.error_description.unwrap_or(error.error),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl TomlConfig {
+    /// Store a login token in a profile, creating the profile if needed.
+    /// A new profile becomes the default one if no default is set yet.
+    pub fn store_login<S>(
+        &mut self,
+        profile_name: S,
+        url: &Url,
+        token: LoginToken,
+    ) -> Result<(), PlatzClientError>
+    where
+        S: Into<String>,
+    {
+        let profile_name = profile_name.into();
+        let credentials = Credentials::AccessToken {
+            access_token: token.access_token,
+            expired_at: token.expires_at,
+            refresh: token.refresh,
+        };
+        match self.profile.get_mut(&profile_name) {
+            Some(profile_info) => {
+                profile_info.url = url.to_string();
+                profile_info.credentials = credentials;
+                Ok(())
+            }
+            None => {
+                let default_profile = self.get_default_profile()?.is_none();
+                self.add_profile(
+                    profile_name,
+                    ProfileInfo {
+                        url: url.to_string(),
+                        credentials,
+                        default_profile,
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Log in using the device code flow and save the resulting token to a
+/// profile in the config file at `conf_path`.
+pub async fn login_to_profile<F>(
+    conf_path: &Path,
+    profile_name: &str,
+    server_url: &Url,
+    login: &DeviceCodeLogin,
+    on_prompt: F,
+) -> Result<(), PlatzClientError>
+where
+    F: FnOnce(&DeviceCodePrompt),
+{
+    let token = login.login(on_prompt).await?;
+    let _lock = ConfigLock::acquire(conf_path).await?;
+    let mut toml_conf = TomlConfig::load(conf_path).await?.unwrap_or_default();
+    toml_conf.store_login(profile_name, server_url, token)?;
+    toml_conf.save(conf_path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(access_token: &str, refresh: bool) -> LoginToken {
+        LoginToken {
+            access_token: access_token.into(),
+            expires_at: Some(Utc::now() + chrono::Duration::hours(1)),
+            refresh: refresh.then(|| RefreshInfo {
+                refresh_token: "refresh".into(),
+                token_url: "https://auth.example.com/token".to_owned(),
+                client_id: "platz".to_owned(),
+            }),
+        }
+    }
+
+    fn access_token(toml_conf: &TomlConfig, profile_name: &str) -> String {
+        match &toml_conf.profile[profile_name].credentials {
+            Credentials::AccessToken { access_token, .. } => {
+                access_token.expose_secret().to_owned()
+            }
+            credentials => panic!("unexpected credentials {credentials:?}"),
+        }
+    }
+
+    #[test]
+    fn first_login_becomes_default() {
+        let url: Url = "https://platz.example.com".parse().unwrap();
+        let mut toml_conf = TomlConfig::default();
+        toml_conf
+            .store_login("prod", &url, token("a", true))
+            .unwrap();
+        toml_conf
+            .store_login("staging", &url, token("b", false))
+            .unwrap();
+        assert_eq!(toml_conf.default_profile_name().unwrap(), Some("prod"));
+        assert_eq!(access_token(&toml_conf, "staging"), "b");
+        assert!(matches!(
+            &toml_conf.profile["prod"].credentials,
+            Credentials::AccessToken { refresh: Some(refresh), .. } if refresh.client_id == "platz"
+        ));
+    }
+
+    #[test]
+    fn login_replaces_profile_credentials() {
+        let mut toml_conf = TomlConfig::default();
+        toml_conf
+            .add_profile(
+                "prod",
+                ProfileInfo {
+                    url: "https://old.example.com/".to_owned(),
+                    credentials: Credentials::UserToken {
+                        user_token: "old".into(),
+                    },
+                    default_profile: true,
+                },
+            )
+            .unwrap();
+        let url: Url = "https://platz.example.com".parse().unwrap();
+        toml_conf
+            .store_login("prod", &url, token("new", false))
+            .unwrap();
+        assert_eq!(toml_conf.profile["prod"].url, "https://platz.example.com/");
+        assert_eq!(access_token(&toml_conf, "prod"), "new");
+        assert!(toml_conf.profile["prod"].default_profile);
+    }
+
+    #[test]
+    fn stored_login_is_saved() {
+        let dir = std::env::temp_dir().join(format!(
+            "platz-sdk-login-{}-{}",
+            std::process::id(),
+            Utc::now().timestamp_nanos_opt().unwrap_or_default()
+        ));
+        let path = dir.join("config.toml");
+        let url: Url = "https://platz.example.com".parse().unwrap();
+        async_std::task::block_on(async {
+            let mut toml_conf = TomlConfig::default();
+            toml_conf
+                .store_login("prod", &url, token("a", true))
+                .unwrap();
+            toml_conf.save(&path).await.unwrap();
+            let loaded = TomlConfig::load(&path).await.unwrap().unwrap();
+            assert_eq!(access_token(&loaded, "prod"), "a");
+            assert_eq!(loaded.default_profile_name().unwrap(), Some("prod"));
+        });
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
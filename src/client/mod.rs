@@ -1,9 +1,30 @@
 mod base;
+mod builder;
 mod config;
+mod config_file;
 mod error;
+mod login;
+mod provider;
+mod registry;
 mod request;
+mod source;
+mod token_command;
 
 pub use base::PlatzClient;
+pub use builder::PlatzClientBuilder;
+pub use config_file::{
+    ConfigLayers, ConfigLock, Credentials, ProfileInfo, RefreshInfo, TomlConfig,
+    PROJECT_CONFIG_FILE_NAME,
+};
 pub use error::PlatzClientError;
+pub use login::{
+    login_to_profile, refresh_access_token, DeviceCodeLogin, DeviceCodePrompt, LoginToken,
+};
+pub use provider::{
+    ChainProvider, ConfigFileProvider, CredentialProvider, EnvProvider, MountedSecretProvider,
+    ProvidedCredentials,
+};
+pub use registry::{PlatzClients, Tagged};
 pub(crate) use request::Paginated;
 pub use request::PlatzRequest;
+pub use source::{ConfigAttempt, ConfigExplanation, ConfigOutcome, CredentialSource};
@@ -0,0 +1,337 @@
+use super::config::{PlatzClientConfig, SECRET_DIR};
+use super::config_file::ConfigLayers;
+use super::error::PlatzClientError;
+use super::source::CredentialSource;
+use async_std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::prelude::*;
+use reqwest::header::{HeaderName, HeaderValue};
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use url::Url;
+
+/// Credentials returned by a `CredentialProvider`, sent with every request.
+#[derive(Debug, Clone)]
+pub struct ProvidedCredentials {
+    pub server_url: Url,
+    pub header_name: HeaderName,
+    pub header_value: HeaderValue,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub source: CredentialSource,
+}
+
+/// A source of credentials for `PlatzClient`, see
+/// `PlatzClientBuilder::provider`.
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    /// Returns the credentials to use for the next request, or `None` if
+    /// the provider isn't configured. This is called before every request,
+    /// so providers should cache their credentials and only get new ones
+    /// when they expire within `refresh_skew`.
+    async fn get_credentials(
+        &self,
+        refresh_skew: chrono::Duration,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError>;
+
+    /// Called when the server rejects the current credentials. Returns
+    /// whether new credentials were loaded, in which case the request is
+    /// retried once.
+    async fn invalidate(&self) -> Result<bool, PlatzClientError> {
+        Ok(false)
+    }
+}
+
+/// A config loaded by one of the built-in providers, refreshed and reloaded
+/// as needed.
+#[derive(Default)]
+struct CachedConfig(Mutex<Option<PlatzClientConfig>>);
+
+impl CachedConfig {
+    async fn get<F, Fut>(
+        &self,
+        refresh_skew: chrono::Duration,
+        load: F,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<PlatzClientConfig>, PlatzClientError>>,
+    {
+        let mut state = self.0.lock().await;
+        if state.is_none() {
+            *state = load().await?;
+        }
+        let Some(config) = state.as_mut() else {
+            return Ok(None);
+        };
+
+        if config.expires_within(refresh_skew) || config.secret_changed().await {
+            match config.refresh(refresh_skew).await {
+                Ok(Some(refreshed)) => *config = refreshed,
+                Ok(None) => (),
+                Err(err) if !config.expired() => {
+                    warn!("Failed refreshing access token, using current one: {err}")
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        if config.expired() {
+            match config.reload().await? {
+                Some(reloaded) => *config = reloaded,
+                None => {
+                    *state = None;
+                    return Ok(None);
+                }
+            }
+        }
+        config.credentials().map(Some)
+    }
+
+    async fn invalidate(&self) -> Result<bool, PlatzClientError> {
+        let mut state = self.0.lock().await;
+        let Some(config) = state.as_mut() else {
+            return Ok(false);
+        };
+        match config.reload().await? {
+            Some(reloaded) if !reloaded.same_credentials(config) => {
+                *config = reloaded;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+/// Credentials from the `PLATZ_URL` environment variable and one of
+/// `PLATZ_API_TOKEN`, `PLATZ_USER_TOKEN` or `PLATZ_ACCESS_TOKEN`.
+/// `PLATZ_API_TOKEN` is sent as an `x-platz-token` header, like a
+/// `user_token` in a config file, and the others are sent as a bearer
+/// token, like an `access_token`. `PLATZ_USER_TOKEN` is deprecated.
+/// `PLATZ_TOKEN_EXPIRES_AT` optionally holds the RFC 3339 expiry time of
+/// `PLATZ_ACCESS_TOKEN`, after which requests fail since the token can't
+/// be refreshed.
+#[derive(Debug, Default)]
+pub struct EnvProvider {
+    strict: bool,
+}
+
+impl EnvProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// In strict mode, a partial or ambiguous configuration is an error
+    /// instead of being ignored: `PLATZ_URL` without a token or the other
+    /// way around, more than one token variable, or `PLATZ_TOKEN_EXPIRES_AT`
+    /// without `PLATZ_ACCESS_TOKEN`.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for EnvProvider {
+    async fn get_credentials(
+        &self,
+        _refresh_skew: chrono::Duration,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError> {
+        let Some(config) = PlatzClientConfig::new_from_env(self.strict)? else {
+            return Ok(None);
+        };
+        let credentials = config.credentials()?;
+        if config.expired()
+            && let Some(expires_at) = credentials.expires_at
+        {
+            return Err(PlatzClientError::EnvConfigError(format!(
+                "PLATZ_ACCESS_TOKEN expired at {expires_at}"
+            )));
+        }
+        Ok(Some(credentials))
+    }
+}
+
+/// Credentials from a config file profile. By default the files described
+/// in `ConfigLayers::load` are used, with the profile selected by
+/// `TomlConfig::selected_profile_name`.
+#[derive(Default)]
+pub struct ConfigFileProvider {
+    config_path: Option<PathBuf>,
+    profile: Option<String>,
+    cached: CachedConfig,
+}
+
+impl ConfigFileProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn config_path<P>(mut self, config_path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.config_path = Some(config_path.into());
+        self
+    }
+
+    pub fn profile<S>(mut self, profile: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Start with an already loaded config instead of loading it on first
+    /// use.
+    pub(super) fn with_config(self, config: PlatzClientConfig) -> Self {
+        Self {
+            cached: CachedConfig(Mutex::new(Some(config))),
+            ..self
+        }
+    }
+
+    async fn load(&self) -> Result<Option<PlatzClientConfig>, PlatzClientError> {
+        let layers = ConfigLayers::load(self.config_path.as_deref()).await?;
+        PlatzClientConfig::from_config_layers(&layers, self.profile.as_deref())
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for ConfigFileProvider {
+    async fn get_credentials(
+        &self,
+        refresh_skew: chrono::Duration,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError> {
+        self.cached.get(refresh_skew, || self.load()).await
+    }
+
+    async fn invalidate(&self) -> Result<bool, PlatzClientError> {
+        self.cached.invalidate().await
+    }
+}
+
+/// Credentials from a mounted `platz-creds` secret, reloaded when the
+/// secret is rotated.
+pub struct MountedSecretProvider {
+    dir: PathBuf,
+    cached: CachedConfig,
+}
+
+impl MountedSecretProvider {
+    /// Read the secret mounted at `/var/run/secrets/platz`.
+    pub fn new() -> Self {
+        Self::with_dir(SECRET_DIR)
+    }
+
+    pub fn with_dir<P>(dir: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            dir: dir.into(),
+            cached: Default::default(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Default for MountedSecretProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for MountedSecretProvider {
+    async fn get_credentials(
+        &self,
+        refresh_skew: chrono::Duration,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError> {
+        self.cached
+            .get(refresh_skew, || {
+                PlatzClientConfig::new_from_secret_dir(&self.dir)
+            })
+            .await
+    }
+
+    async fn invalidate(&self) -> Result<bool, PlatzClientError> {
+        self.cached.invalidate().await
+    }
+}
+
+/// Tries providers in order and uses the first one that's configured. The
+/// chain stops at the first provider that fails. Once a provider is
+/// selected it keeps being used until it's no longer configured, and then
+/// the other providers are tried again.
+#[derive(Default)]
+pub struct ChainProvider {
+    providers: Vec<Box<dyn CredentialProvider>>,
+    selected: Mutex<Option<usize>>,
+}
+
+impl ChainProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The providers used by `PlatzClient::new`: environment variables,
+    /// then config files, then a mounted secret.
+    pub fn default_chain() -> Self {
+        Self::default_chain_with(EnvProvider::new())
+    }
+
+    /// Same as `default_chain` with a configured `EnvProvider`.
+    pub fn default_chain_with(env_provider: EnvProvider) -> Self {
+        Self::new()
+            .with(env_provider)
+            .with(ConfigFileProvider::new())
+            .with(MountedSecretProvider::new())
+    }
+
+    pub fn with<P>(mut self, provider: P) -> Self
+    where
+        P: CredentialProvider + 'static,
+    {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for ChainProvider {
+    async fn get_credentials(
+        &self,
+        refresh_skew: chrono::Duration,
+    ) -> Result<Option<ProvidedCredentials>, PlatzClientError> {
+        let mut selected = self.selected.lock().await;
+        let previous = selected.take();
+        if let Some(index) = previous
+            && let Some(credentials) = self.providers[index].get_credentials(refresh_skew).await?
+        {
+            *selected = Some(index);
+            return Ok(Some(credentials));
+        }
+        for (index, provider) in self.providers.iter().enumerate() {
+            if Some(index) == previous {
+                continue;
+            }
+            if let Some(credentials) = provider.get_credentials(refresh_skew).await? {
+                *selected = Some(index);
+                return Ok(Some(credentials));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn invalidate(&self) -> Result<bool, PlatzClientError> {
+        let selected = *self.selected.lock().await;
+        match selected {
+            Some(index) => self.providers[index].invalidate().await,
+            None => Ok(false),
+        }
+    }
+}
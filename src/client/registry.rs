@@ -0,0 +1,123 @@
+use super::base::PlatzClient;
+use super::config::PlatzClientConfig;
+use super::config_file::{ConfigLayers, TomlConfig};
+use super::error::PlatzClientError;
+use super::provider::ConfigFileProvider;
+use futures::future::join_all;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+
+/// An item returned from one of the servers in a `PlatzClients` registry,
+/// tagged with the name of its profile.
+#[derive(Debug, Clone)]
+pub struct Tagged<T> {
+    pub profile: String,
+    pub item: T,
+}
+
+/// A client for every profile in a config file, for tools working across
+/// several Platz installations.
+pub struct PlatzClients {
+    clients: BTreeMap<String, PlatzClient>,
+}
+
+impl PlatzClients {
+    /// Load all profiles from the config files used by `PlatzClient`, see
+    /// `ConfigLayers::load`.
+    pub async fn new() -> Result<Self, PlatzClientError> {
+        let layers = ConfigLayers::load(None).await?;
+        if layers.is_empty() {
+            return Err(PlatzClientError::NoConfigFound);
+        }
+        let clients = layers
+            .merged()
+            .profile
+            .into_keys()
+            .map(|name| {
+                let config = PlatzClientConfig::from_config_layers(&layers, Some(&name))?
+                    .ok_or(PlatzClientError::NoConfigFound)?;
+                let provider = ConfigFileProvider::new()
+                    .profile(name.as_str())
+                    .with_config(config);
+                Ok((name, PlatzClient::from_provider(Box::new(provider))))
+            })
+            .collect::<Result<_, PlatzClientError>>()?;
+        Ok(Self { clients })
+    }
+
+    /// Load all profiles from a config file.
+    pub async fn from_config_file(conf_path: &Path) -> Result<Self, PlatzClientError> {
+        let toml_conf = TomlConfig::load(conf_path)
+            .await?
+            .ok_or(PlatzClientError::NoConfigFound)?;
+        let clients = toml_conf
+            .profile
+            .iter()
+            .map(|(name, profile_info)| {
+                let provider = ConfigFileProvider::new()
+                    .config_path(conf_path)
+                    .profile(name.as_str())
+                    .with_config(profile_info.to_client(conf_path, name)?);
+                Ok((name.clone(), PlatzClient::from_provider(Box::new(provider))))
+            })
+            .collect::<Result<_, PlatzClientError>>()?;
+        Ok(Self { clients })
+    }
+
+    pub fn get(&self, profile_name: &str) -> Option<&PlatzClient> {
+        self.clients.get(profile_name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.clients.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PlatzClient)> {
+        self.clients
+            .iter()
+            .map(|(name, client)| (name.as_str(), client))
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Run the same query against every server concurrently, returning each
+    /// server's result separately.
+    pub async fn fan_out<'a, F, Fut, T>(&'a self, f: F) -> Vec<Tagged<anyhow::Result<T>>>
+    where
+        F: Fn(&'a PlatzClient) -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let names = self.clients.keys().cloned();
+        let results = join_all(self.clients.values().map(f)).await;
+        names
+            .zip(results)
+            .map(|(profile, item)| Tagged { profile, item })
+            .collect()
+    }
+
+    /// Run the same list query against every server concurrently and merge
+    /// the results, tagging each item with its profile. Fails if any of the
+    /// servers fails.
+    pub async fn fan_out_merged<'a, F, Fut, T>(&'a self, f: F) -> anyhow::Result<Vec<Tagged<T>>>
+    where
+        F: Fn(&'a PlatzClient) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<T>>>,
+    {
+        let mut merged = Vec::new();
+        for Tagged { profile, item } in self.fan_out(f).await {
+            let items = item.map_err(|err| err.context(format!("Profile {profile}")))?;
+            merged.extend(items.into_iter().map(|item| Tagged {
+                profile: profile.clone(),
+                item,
+            }));
+        }
+        Ok(merged)
+    }
+}
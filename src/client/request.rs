@@ -1,7 +1,7 @@
 use super::base::PlatzClient;
 use super::error::PlatzClientError;
 use async_trait::async_trait;
-use reqwest::{ClientBuilder, RequestBuilder};
+use reqwest::{ClientBuilder, RequestBuilder, StatusCode};
 use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::collections::HashMap;
 use tracing::instrument;
@@ -68,7 +68,7 @@ impl<'a> PlatzRequest<'a> {
     }
 
     pub async fn request_builder(&self) -> Result<RequestBuilder, PlatzClientError> {
-        let (header_key, header_value) = self.client.authorization().await?;
+        let credentials = self.client.credentials().await?;
         Ok(ClientBuilder::new()
             .user_agent(HTTP_USER_AGENT.clone())
             .gzip(true)
@@ -77,19 +77,31 @@ impl<'a> PlatzRequest<'a> {
             .build()?
             .request(
                 self.method.clone(),
-                self.client.build_url(&self.path).await?,
+                credentials
+                    .server_url
+                    .join(&self.path)
+                    .map_err(PlatzClientError::UrlJoinError)?,
             )
-            .header(header_key, header_value)
+            .header(credentials.header_name, credentials.header_value)
             .query(&self.query))
     }
 
+    /// Send the request, and if the server responds with 401 Unauthorized,
+    /// reload the client's credentials and retry once if they changed.
+    async fn execute<F>(&self, prepare: F) -> Result<reqwest::Response, PlatzClientError>
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder,
+    {
+        let mut response = prepare(self.request_builder().await?).send().await?;
+        if response.status() == StatusCode::UNAUTHORIZED && self.client.reload_credentials().await?
+        {
+            response = prepare(self.request_builder().await?).send().await?;
+        }
+        response.error_for_status_with_body().await
+    }
+
     pub async fn send_with_no_response(self) -> Result<(), PlatzClientError> {
-        self.request_builder()
-            .await?
-            .send()
-            .await?
-            .error_for_status_with_body()
-            .await?;
+        self.execute(|request| request).await?;
         Ok(())
     }
 
@@ -97,15 +109,7 @@ impl<'a> PlatzRequest<'a> {
     where
         T: DeserializeOwned + Send,
     {
-        Ok(self
-            .request_builder()
-            .await?
-            .send()
-            .await?
-            .error_for_status_with_body()
-            .await?
-            .json()
-            .await?)
+        Ok(self.execute(|request| request).await?.json().await?)
     }
 
     #[instrument(skip_all, fields(path=self.path))]
@@ -115,12 +119,7 @@ impl<'a> PlatzRequest<'a> {
         R: DeserializeOwned + Send,
     {
         Ok(self
-            .request_builder()
-            .await?
-            .json(&body)
-            .send()
-            .await?
-            .error_for_status_with_body()
+            .execute(|request| request.json(&body))
             .await?
             .json()
             .await?)
@@ -140,12 +139,7 @@ impl<'a> PlatzRequest<'a> {
             paging_info.push(("page_size", size.to_string()))
         }
         let page = self
-            .request_builder()
-            .await?
-            .query(&paging_info)
-            .send()
-            .await?
-            .error_for_status_with_body()
+            .execute(|request| request.query(&paging_info))
             .await?
             .json()
             .await?;
@@ -208,3 +202,103 @@ impl ResponseExt for reqwest::Response {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::provider::MountedSecretProvider;
+    use super::*;
+    use async_std::io::prelude::*;
+    use async_std::io::BufReader;
+    use async_std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn write_secret(dir: &Path, server_url: &str, access_token: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join("server_url"), server_url).unwrap();
+        std::fs::write(dir.join("expires_at"), "2999-01-01T00:00:00Z").unwrap();
+        std::fs::write(dir.join("access_token"), access_token).unwrap();
+    }
+
+    /// Answer requests bearing `valid_token` with an empty JSON object and
+    /// everything else with 401, counting the requests.
+    async fn serve(listener: TcpListener, valid_token: &'static str, requests: Arc<AtomicUsize>) {
+        while let Ok((stream, _)) = listener.accept().await {
+            requests.fetch_add(1, Ordering::SeqCst);
+            let mut reader = BufReader::new(&stream);
+            let mut authorized = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).await.unwrap() > 2 {
+                authorized |= line
+                    .trim()
+                    .eq_ignore_ascii_case(&format!("authorization: Bearer {valid_token}"));
+                line.clear();
+            }
+            let response = if authorized {
+                "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}"
+            } else {
+                "HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            };
+            (&stream).write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    async fn client(valid_token: &'static str) -> (PlatzClient, PathBuf, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_url = format!("http://{}/", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        async_std::task::spawn(serve(listener, valid_token, requests.clone()));
+        let dir = std::env::temp_dir().join(format!(
+            "platz-sdk-retry-{valid_token}-{}",
+            std::process::id()
+        ));
+        write_secret(&dir, &server_url, "old");
+        let client = PlatzClient::builder()
+            .provider(MountedSecretProvider::with_dir(&dir))
+            .build()
+            .await
+            .unwrap();
+        (client, dir, requests)
+    }
+
+    /// reqwest runs on tokio, so drive the client with a tokio runtime.
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn retries_with_rotated_secret_after_401() {
+        block_on(async {
+            let (client, dir, requests) = client("new").await;
+            let server_url = std::fs::read_to_string(dir.join("server_url")).unwrap();
+            write_secret(&dir, &server_url, "new");
+            let response: serde_json::Value = client
+                .request(reqwest::Method::GET, "/api/v2/deployments")
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response, serde_json::json!({}));
+            assert_eq!(requests.load(Ordering::SeqCst), 2);
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+
+    #[test]
+    fn doesnt_retry_with_unchanged_credentials() {
+        block_on(async {
+            let (client, dir, requests) = client("other").await;
+            let result: Result<serde_json::Value, _> = client
+                .request(reqwest::Method::GET, "/api/v2/deployments")
+                .send()
+                .await;
+            assert!(matches!(result, Err(PlatzClientError::HttpError(_))));
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+            std::fs::remove_dir_all(dir).unwrap();
+        });
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::path::PathBuf;
+
+/// Where a client's credentials were loaded from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// Environment variables holding the server URL and token.
+    Env {
+        url_var: &'static str,
+        token_var: &'static str,
+    },
+    /// A profile in a `platz/config.toml` file.
+    ConfigFile { path: PathBuf, profile: String },
+    /// A mounted `platz-creds` secret.
+    MountedSecret { dir: PathBuf },
+    /// A custom `CredentialProvider`.
+    Provider { name: String },
+}
+
+impl fmt::Display for CredentialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env { url_var, token_var } => {
+                write!(f, "environment variables {url_var} and {token_var}")
+            }
+            Self::ConfigFile { path, profile } => {
+                write!(f, "profile {profile:?} in {}", path.display())
+            }
+            Self::MountedSecret { dir } => write!(f, "secret mounted at {}", dir.display()),
+            Self::Provider { name } => write!(f, "credential provider {name}"),
+        }
+    }
+}
+
+/// What happened when trying a single credential source.
+#[derive(Debug, Clone)]
+pub enum ConfigOutcome {
+    /// The source was used by the client.
+    Selected(CredentialSource),
+    /// The source holds valid credentials, but a source tried before it
+    /// was selected.
+    Shadowed(CredentialSource),
+    /// The source isn't configured.
+    Skipped(String),
+    /// The source is configured but couldn't be loaded. The client fails
+    /// with this error if no source before it was selected.
+    Failed(String),
+}
+
+/// A credential source that was tried, in the order sources are tried.
+#[derive(Debug, Clone)]
+pub struct ConfigAttempt {
+    pub description: String,
+    pub outcome: ConfigOutcome,
+}
+
+/// Report of every credential source `PlatzClient::new` tries, see
+/// `PlatzClient::explain_config`.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigExplanation {
+    pub attempts: Vec<ConfigAttempt>,
+}
+
+impl ConfigExplanation {
+    pub fn selected(&self) -> Option<&CredentialSource> {
+        self.attempts
+            .iter()
+            .find_map(|attempt| match &attempt.outcome {
+                ConfigOutcome::Selected(source) => Some(source),
+                _ => None,
+            })
+    }
+}
+
+impl fmt::Display for ConfigExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, attempt) in self.attempts.iter().enumerate() {
+            write!(f, "{}. {}: ", index + 1, attempt.description)?;
+            match &attempt.outcome {
+                ConfigOutcome::Selected(source) => writeln!(f, "selected ({source})")?,
+                ConfigOutcome::Shadowed(source) => {
+                    writeln!(f, "available but not used ({source})")?
+                }
+                ConfigOutcome::Skipped(reason) => writeln!(f, "skipped ({reason})")?,
+                ConfigOutcome::Failed(error) => writeln!(f, "failed ({error})")?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+use super::error::PlatzClientError;
+use crate::SecretString;
+use async_std::task::spawn_blocking;
+use chrono::prelude::*;
+use serde::Deserialize;
+use std::io::Read;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use zeroize::Zeroize;
+
+/// How long a `token_command` may run when the profile doesn't set
+/// `token_command_timeout_secs`.
+pub(super) const DEFAULT_TOKEN_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What a `token_command` prints to stdout, either
+/// `{"access_token": "...", "expires_at": "2024-01-01T00:00:00Z"}` or
+/// `{"user_token": "..."}`. `expires_at` is optional, and when set the
+/// token is reused until it's about to expire.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(super) enum TokenCommandOutput {
+    AccessToken {
+        access_token: SecretString,
+        expires_at: Option<DateTime<Utc>>,
+    },
+    UserToken {
+        user_token: SecretString,
+    },
+}
+
+/// A credential helper command, killed if it runs longer than `timeout`.
+#[derive(Debug, Clone)]
+pub(super) struct TokenCommand {
+    pub command: Vec<String>,
+    pub timeout: Duration,
+}
+
+struct CommandOutput {
+    status: ExitStatus,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+impl TokenCommand {
+    /// Run the command and parse the token it prints.
+    pub async fn run(&self) -> Result<TokenCommandOutput, PlatzClientError> {
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(PlatzClientError::TokenCommandError(
+                "token_command is empty".to_owned(),
+            ));
+        };
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let timeout = self.timeout;
+        let mut output = spawn_blocking(move || output_with_timeout(cmd, timeout))
+            .await
+            .map_err(|err| PlatzClientError::TokenCommandError(format!("{program}: {err}")))?
+            .ok_or_else(|| {
+                PlatzClientError::TokenCommandError(format!(
+                    "{program} timed out after {}s",
+                    timeout.as_secs_f64()
+                ))
+            })?;
+
+        if !output.status.success() {
+            output.stdout.zeroize();
+            return Err(PlatzClientError::TokenCommandError(format!(
+                "{program} failed with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let parsed = serde_json::from_slice(&output.stdout).map_err(|err| {
+            PlatzClientError::TokenCommandError(format!("Invalid output from {program}: {err}"))
+        });
+        output.stdout.zeroize();
+        parsed
+    }
+}
+
+/// Run a command to completion, or kill it after `timeout` and return
+/// `None`.
+fn output_with_timeout(
+    mut cmd: Command,
+    timeout: Duration,
+) -> std::io::Result<Option<CommandOutput>> {
+    let deadline = Instant::now() + timeout;
+    let mut child = cmd.spawn()?;
+    // Pipes are read on their own threads so a command printing a lot
+    // doesn't block on a full pipe.
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            // The reader threads are left to finish whenever processes the
+            // command started close the pipes.
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(WAIT_INTERVAL);
+    };
+    Ok(Some(CommandOutput {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+fn read_in_background<R>(pipe: Option<R>) -> std::thread::JoinHandle<Vec<u8>>
+where
+    R: Read + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::client::config_file::{Credentials, ProfileInfo};
+    use crate::client::provider::{ConfigFileProvider, CredentialProvider};
+    use async_std::task::block_on;
+    use std::path::{Path, PathBuf};
+
+    /// A directory holding a helper script that runs `body`.
+    struct Helper {
+        dir: PathBuf,
+    }
+
+    impl Helper {
+        fn new(name: &str, body: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "platz-sdk-token-command-{name}-{}",
+                std::process::id()
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("helper.sh"), body).unwrap();
+            Self { dir }
+        }
+
+        fn command(&self, timeout: Duration) -> TokenCommand {
+            TokenCommand {
+                command: vec![
+                    "/bin/sh".to_owned(),
+                    self.dir.join("helper.sh").to_string_lossy().into_owned(),
+                    self.dir.to_string_lossy().into_owned(),
+                ],
+                timeout,
+            }
+        }
+
+        fn runs(&self) -> usize {
+            std::fs::read_to_string(self.dir.join("runs"))
+                .map(|runs| runs.lines().count())
+                .unwrap_or_default()
+        }
+    }
+
+    impl Drop for Helper {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn run(helper: &Helper) -> Result<TokenCommandOutput, PlatzClientError> {
+        block_on(helper.command(DEFAULT_TOKEN_COMMAND_TIMEOUT).run())
+    }
+
+    fn error_message(result: Result<TokenCommandOutput, PlatzClientError>) -> String {
+        match result {
+            Err(PlatzClientError::TokenCommandError(message)) => message,
+            Err(err) => panic!("unexpected error {err}"),
+            Ok(_) => panic!("token command succeeded"),
+        }
+    }
+
+    #[test]
+    fn access_token_output() {
+        let helper = Helper::new(
+            "access",
+            r#"echo '{"access_token": "secret", "expires_at": "2030-01-01T00:00:00Z"}'"#,
+        );
+        let TokenCommandOutput::AccessToken {
+            access_token,
+            expires_at,
+        } = run(&helper).unwrap()
+        else {
+            panic!("expected an access token");
+        };
+        assert_eq!(access_token.expose_secret(), "secret");
+        assert_eq!(expires_at, Some("2030-01-01T00:00:00Z".parse().unwrap()));
+    }
+
+    #[test]
+    fn user_token_output() {
+        let helper = Helper::new("user", r#"echo '{"user_token": "secret"}'"#);
+        let TokenCommandOutput::UserToken { user_token } = run(&helper).unwrap() else {
+            panic!("expected a user token");
+        };
+        assert_eq!(user_token.expose_secret(), "secret");
+    }
+
+    #[test]
+    fn non_zero_exit() {
+        let helper = Helper::new("fail", "echo 'vault is sealed' >&2; exit 3");
+        let message = error_message(run(&helper));
+        assert!(message.contains("vault is sealed"), "{message}");
+    }
+
+    #[test]
+    fn invalid_output() {
+        let helper = Helper::new("invalid", "echo 'token: secret'");
+        let message = error_message(run(&helper));
+        assert!(message.starts_with("Invalid output"), "{message}");
+        assert!(!message.contains("secret"), "{message}");
+    }
+
+    #[test]
+    fn times_out() {
+        let helper = Helper::new("hang", "sleep 10");
+        let started_at = Instant::now();
+        let message = error_message(block_on(helper.command(Duration::from_millis(200)).run()));
+        assert!(message.contains("timed out"), "{message}");
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+    }
+
+    fn provider(helper: &Helper) -> ConfigFileProvider {
+        let TokenCommand { command, .. } = helper.command(DEFAULT_TOKEN_COMMAND_TIMEOUT);
+        let profile = ProfileInfo {
+            url: "https://platz.example.com".to_owned(),
+            credentials: Credentials::TokenCommand {
+                token_command: command,
+                token_command_timeout_secs: None,
+            },
+            default_profile: true,
+        };
+        let config = profile
+            .to_client(Path::new("config.toml"), "default")
+            .unwrap();
+        ConfigFileProvider::new().with_config(config)
+    }
+
+    fn get_token(provider: &ConfigFileProvider) -> String {
+        let credentials = block_on(provider.get_credentials(chrono::Duration::seconds(60)))
+            .unwrap()
+            .unwrap();
+        credentials.header_value.to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn caches_token_until_expiry() {
+        let helper = Helper::new(
+            "cached",
+            r#"echo run >> "$1/runs"
+echo '{"access_token": "secret", "expires_at": "2030-01-01T00:00:00Z"}'"#,
+        );
+        let provider = provider(&helper);
+        assert_eq!(helper.runs(), 0);
+        assert_eq!(get_token(&provider), "Bearer secret");
+        assert_eq!(get_token(&provider), "Bearer secret");
+        assert_eq!(helper.runs(), 1);
+    }
+
+    #[test]
+    fn reruns_expiring_token() {
+        // The token expires within the refresh skew, so every request runs
+        // the command again.
+        let expires_at = (Utc::now() + chrono::Duration::seconds(10)).to_rfc3339();
+        let helper = Helper::new(
+            "expiring",
+            &format!(
+                r#"echo run >> "$1/runs"
+echo '{{"access_token": "secret", "expires_at": "{expires_at}"}}'"#
+            ),
+        );
+        let provider = provider(&helper);
+        get_token(&provider);
+        get_token(&provider);
+        assert_eq!(helper.runs(), 2);
+    }
+
+    #[test]
+    fn user_token_is_never_rerun() {
+        let helper = Helper::new(
+            "user-cached",
+            r#"echo run >> "$1/runs"
+echo '{"user_token": "secret"}'"#,
+        );
+        let provider = provider(&helper);
+        get_token(&provider);
+        get_token(&provider);
+        assert_eq!(helper.runs(), 1);
+    }
+}
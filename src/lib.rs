@@ -1,7 +1,9 @@
 mod client;
 mod resources;
+mod status;
 mod types;
 
 pub use client::*;
 pub use resources::*;
+pub use status::*;
 pub use types::*;
@@ -0,0 +1,649 @@
+use crate::client::PlatzClient;
+use crate::{
+    Deployment, DeploymentFilters, DeploymentId, DeploymentResource, DeploymentResourceFilters,
+    DeploymentResourceType, DeploymentResourceTypeFilters, DeploymentResourceTypeId, EnvId,
+    K8sCluster, K8sClusterFilters, K8sClusterId, NewDeployment, Secret, SecretFilters,
+};
+use anyhow::{bail, Result};
+use futures::future::try_join_all;
+use platz_chart_ext::UiSchemaInputSingleType;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Where to clone a deployment to, see `PlatzClient::clone_deployment`.
+#[derive(Debug, Clone)]
+pub struct CloneDeploymentTarget {
+    pub env_id: EnvId,
+    /// The cluster to create the deployment on. If not set, the target env's
+    /// only cluster that isn't ignored is used, or the one in the same
+    /// region as the source deployment's cluster.
+    pub cluster_id: Option<K8sClusterId>,
+    /// Name of the new deployment, defaults to the source deployment's name.
+    pub name: Option<String>,
+    /// Only compute the new deployment without creating it.
+    pub dry_run: bool,
+}
+
+impl CloneDeploymentTarget {
+    pub fn new(env_id: EnvId) -> Self {
+        Self {
+            env_id,
+            cluster_id: None,
+            name: None,
+            dry_run: false,
+        }
+    }
+}
+
+/// A config reference that was changed to the matching secret or
+/// deployment resource in the target env.
+#[derive(Debug, Clone, Serialize)]
+pub struct RemappedReference {
+    pub input_id: String,
+    pub source_id: String,
+    pub target_id: String,
+}
+
+/// A config reference with no match in the target env.
+#[derive(Debug, Clone, Serialize)]
+pub struct UnresolvedReference {
+    pub input_id: String,
+    pub source_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClonedDeployment {
+    pub new_deployment: NewDeployment,
+    pub remapped: Vec<RemappedReference>,
+    /// References still present in the config after the mapping hook ran.
+    /// The deployment is only created when there are none.
+    pub unresolved: Vec<UnresolvedReference>,
+    /// The created deployment, `None` in dry-run mode.
+    pub created: Option<Deployment>,
+}
+
+impl PlatzClient {
+    /// Clone a deployment to another env, using the same helm chart. Config
+    /// inputs that select secrets or deployment resources are changed to the
+    /// ones with the same name in the target env.
+    pub async fn clone_deployment(
+        &self,
+        source_id: DeploymentId,
+        target: CloneDeploymentTarget,
+    ) -> Result<ClonedDeployment> {
+        self.clone_deployment_with(source_id, target, |_input_id, value| Ok(value))
+            .await
+    }
+
+    /// Same as `clone_deployment`, with a hook called with each config
+    /// input's id and value after references were remapped. It returns the
+    /// value to use in the new deployment.
+    pub async fn clone_deployment_with<F>(
+        &self,
+        source_id: DeploymentId,
+        target: CloneDeploymentTarget,
+        mut map_config: F,
+    ) -> Result<ClonedDeployment>
+    where
+        F: FnMut(&str, Value) -> Result<Value>,
+    {
+        let source = self.deployment(source_id).await?;
+        let (clusters, helm_chart) = futures::try_join!(
+            self.k8s_clusters(K8sClusterFilters::default()),
+            self.helm_chart(source.helm_chart_id),
+        )?;
+        let Some(source_cluster) = clusters
+            .iter()
+            .find(|cluster| cluster.id == source.cluster_id)
+        else {
+            bail!("Cluster of deployment {} not found", source.name);
+        };
+        let Some(source_env_id) = source_cluster.env_id else {
+            bail!("Cluster {} is not in an env", source_cluster.name);
+        };
+        let cluster_id = target_cluster(&clusters, source_cluster, &target)?;
+
+        let collection_inputs: HashSet<String> = helm_chart
+            .values_ui
+            .iter()
+            .flat_map(|values_ui| values_ui.get_inputs())
+            .filter(|input| {
+                matches!(
+                    input.input_type.single_type,
+                    UiSchemaInputSingleType::CollectionSelect { .. }
+                )
+            })
+            .map(|input| input.id.clone())
+            .collect();
+        let references = if collection_inputs.is_empty() {
+            References::default()
+        } else {
+            self.clone_references(source_env_id, target.env_id).await?
+        };
+
+        let mut remapped = Vec::new();
+        let mut unresolved = Vec::new();
+        let config = match source.config {
+            Value::Object(inputs) => {
+                let mut config = serde_json::Map::new();
+                for (input_id, mut value) in inputs {
+                    let mut input_unresolved = Vec::new();
+                    if collection_inputs.contains(&input_id) {
+                        references.remap_value(
+                            &input_id,
+                            &mut value,
+                            &mut remapped,
+                            &mut input_unresolved,
+                        );
+                    }
+                    let value = map_config(&input_id, value)?;
+                    unresolved.extend(
+                        input_unresolved
+                            .into_iter()
+                            .filter(|reference| contains_string(&value, &reference.source_id)),
+                    );
+                    config.insert(input_id, value);
+                }
+                Value::Object(config)
+            }
+            config => config,
+        };
+
+        let new_deployment = NewDeployment {
+            name: target.name.clone().unwrap_or(source.name),
+            kind_id: source.kind_id,
+            cluster_id,
+            helm_chart_id: source.helm_chart_id,
+            config: Some(config),
+            values_override: source.values_override,
+        };
+        let created = if target.dry_run {
+            None
+        } else if !unresolved.is_empty() {
+            bail!(
+                "Config references with no match in the target env: {}",
+                unresolved
+                    .iter()
+                    .map(|reference| format!("{}: {}", reference.input_id, reference.reason))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        } else {
+            Some(self.create_deployment(new_deployment.clone()).await?)
+        };
+        Ok(ClonedDeployment {
+            new_deployment,
+            remapped,
+            unresolved,
+            created,
+        })
+    }
+
+    async fn clone_references(
+        &self,
+        source_env_id: EnvId,
+        target_env_id: EnvId,
+    ) -> Result<References> {
+        let (source_secrets, target_secrets, types, deployments) = futures::try_join!(
+            self.secrets(SecretFilters {
+                env_id: Some(source_env_id),
+                ..Default::default()
+            }),
+            self.secrets(SecretFilters {
+                env_id: Some(target_env_id),
+                ..Default::default()
+            }),
+            self.deployment_resource_types(DeploymentResourceTypeFilters::default()),
+            self.deployments(DeploymentFilters {
+                env_id: Some(target_env_id),
+                ..Default::default()
+            }),
+        )?;
+        // Types that aren't in any env can't be selected with a filter, so
+        // keep those and the ones of both envs here.
+        let resource_types: HashMap<DeploymentResourceTypeId, DeploymentResourceType> = types
+            .into_iter()
+            .filter(|resource_type| {
+                resource_type
+                    .env_id
+                    .is_none_or(|env_id| env_id == source_env_id || env_id == target_env_id)
+            })
+            .map(|resource_type| (resource_type.id, resource_type))
+            .collect();
+        let resources = try_join_all(resource_types.keys().map(|type_id| {
+            self.deployment_resources(DeploymentResourceFilters {
+                type_id: Some(*type_id),
+            })
+        }))
+        .await?
+        .into_iter()
+        .flatten()
+        .collect();
+        Ok(References {
+            target_env_id: Some(target_env_id),
+            source_secrets,
+            target_secrets,
+            resources,
+            resource_types,
+            target_deployment_ids: deployments
+                .into_iter()
+                .map(|deployment| deployment.id)
+                .collect(),
+        })
+    }
+}
+
+fn target_cluster(
+    clusters: &[K8sCluster],
+    source_cluster: &K8sCluster,
+    target: &CloneDeploymentTarget,
+) -> Result<K8sClusterId> {
+    if let Some(cluster_id) = target.cluster_id {
+        return match clusters.iter().find(|cluster| cluster.id == cluster_id) {
+            Some(cluster) if cluster.env_id == Some(target.env_id) => Ok(cluster_id),
+            Some(cluster) => bail!("Cluster {} is not in the target env", cluster.name),
+            None => bail!("Cluster {cluster_id} not found"),
+        };
+    }
+    let candidates: Vec<&K8sCluster> = clusters
+        .iter()
+        .filter(|cluster| cluster.env_id == Some(target.env_id) && !cluster.ignore)
+        .collect();
+    let same_region: Vec<&K8sCluster> = candidates
+        .iter()
+        .copied()
+        .filter(|cluster| cluster.region_name == source_cluster.region_name)
+        .collect();
+    match (candidates.as_slice(), same_region.as_slice()) {
+        ([], _) => bail!("The target env has no clusters"),
+        ([cluster], _) | (_, [cluster]) => Ok(cluster.id),
+        _ => bail!("The target env has several clusters, set the target cluster_id"),
+    }
+}
+
+fn contains_string(value: &Value, s: &str) -> bool {
+    match value {
+        Value::String(value) => value == s,
+        Value::Array(values) => values.iter().any(|value| contains_string(value, s)),
+        Value::Object(map) => map.values().any(|value| contains_string(value, s)),
+        _ => false,
+    }
+}
+
+/// Secrets and deployment resources that config inputs may refer to.
+#[derive(Default)]
+struct References {
+    target_env_id: Option<EnvId>,
+    source_secrets: Vec<Secret>,
+    target_secrets: Vec<Secret>,
+    resources: Vec<DeploymentResource>,
+    resource_types: HashMap<DeploymentResourceTypeId, DeploymentResourceType>,
+    target_deployment_ids: HashSet<DeploymentId>,
+}
+
+impl References {
+    /// Replace the ids in a `CollectionSelect` input's value, which is a
+    /// single id or an array of them.
+    fn remap_value(
+        &self,
+        input_id: &str,
+        value: &mut Value,
+        remapped: &mut Vec<RemappedReference>,
+        unresolved: &mut Vec<UnresolvedReference>,
+    ) {
+        match value {
+            Value::String(source_id) => match self.remap_id(source_id) {
+                None => (),
+                Some(Ok(target_id)) => {
+                    remapped.push(RemappedReference {
+                        input_id: input_id.to_owned(),
+                        source_id: source_id.clone(),
+                        target_id: target_id.clone(),
+                    });
+                    *source_id = target_id;
+                }
+                Some(Err(reason)) => unresolved.push(UnresolvedReference {
+                    input_id: input_id.to_owned(),
+                    source_id: source_id.clone(),
+                    reason,
+                }),
+            },
+            Value::Array(values) => {
+                for value in values {
+                    self.remap_value(input_id, value, remapped, unresolved);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    /// The id of the matching item in the target env, or `None` if `id`
+    /// isn't a known secret or deployment resource.
+    fn remap_id(&self, id: &str) -> Option<Result<String, String>> {
+        if let Some(secret) = self
+            .source_secrets
+            .iter()
+            .find(|secret| secret.id.to_string() == id)
+        {
+            return Some(
+                self.target_secrets
+                    .iter()
+                    .find(|target| {
+                        target.collection == secret.collection && target.name == secret.name
+                    })
+                    .map(|target| target.id.to_string())
+                    .ok_or_else(|| {
+                        format!("no {} secret named {}", secret.collection, secret.name)
+                    }),
+            );
+        }
+
+        let resource = self
+            .resources
+            .iter()
+            .find(|resource| resource.id.to_string() == id)?;
+        let Some(source_type) = self.resource_types.get(&resource.type_id) else {
+            return Some(Err(format!(
+                "deployment resource type of {} not found",
+                resource.name
+            )));
+        };
+        let target_type_id = if source_type.env_id.is_none() {
+            Some(source_type.id)
+        } else {
+            self.resource_types
+                .values()
+                .find(|target_type| {
+                    target_type.env_id == self.target_env_id
+                        && target_type.key == source_type.key
+                        && target_type.deployment_kind_id == source_type.deployment_kind_id
+                })
+                .map(|target_type| target_type.id)
+        };
+        let Some(target_type_id) = target_type_id else {
+            return Some(Err(format!("no {} resource type", source_type.key)));
+        };
+        Some(
+            self.resources
+                .iter()
+                .find(|target| {
+                    target.type_id == target_type_id
+                        && target.name == resource.name
+                        && target.deployment_id.is_none_or(|deployment_id| {
+                            self.target_deployment_ids.contains(&deployment_id)
+                        })
+                })
+                .map(|target| target.id.to_string())
+                .ok_or_else(|| format!("no {} resource named {}", source_type.key, resource.name)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn cluster(env_id: EnvId, name: &str, region_name: &str) -> K8sCluster {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "env_id": env_id,
+            "provider_id": name,
+            "created_at": "2024-01-01T00:00:00Z",
+            "last_seen_at": "2024-01-01T00:00:00Z",
+            "name": name,
+            "region_name": region_name,
+            "is_ok": true,
+            "not_ok_reason": null,
+            "ignore": false,
+            "ingress_domain": null,
+            "ingress_class": null,
+            "ingress_tls_secret_name": null,
+            "grafana_url": null,
+            "grafana_datasource_name": null,
+        }))
+        .unwrap()
+    }
+
+    fn secret(env_id: EnvId, collection: &str, name: &str) -> Secret {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "env_id": env_id,
+            "collection": collection,
+            "name": name,
+        }))
+        .unwrap()
+    }
+
+    fn resource_type(env_id: Option<EnvId>, key: &str) -> DeploymentResourceType {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "env_id": env_id,
+            "deployment_kind_id": Uuid::nil(),
+            "key": key,
+            "spec": {},
+        }))
+        .unwrap()
+    }
+
+    fn resource(
+        resource_type: &DeploymentResourceType,
+        deployment_id: Option<DeploymentId>,
+        name: &str,
+    ) -> DeploymentResource {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "type_id": resource_type.id,
+            "deployment_id": deployment_id,
+            "name": name,
+            "exists": true,
+            "props": {},
+            "sync_status": "Ready",
+            "sync_reason": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn selects_target_cluster() {
+        let source_env_id = EnvId::from(Uuid::new_v4());
+        let target_env_id = EnvId::from(Uuid::new_v4());
+        let source = cluster(source_env_id, "source", "eu-west-1");
+        let target = CloneDeploymentTarget::new(target_env_id);
+
+        let only = cluster(target_env_id, "only", "us-east-1");
+        let clusters = vec![source.clone(), only.clone()];
+        assert_eq!(
+            target_cluster(&clusters, &source, &target).unwrap(),
+            only.id
+        );
+
+        let same_region = cluster(target_env_id, "same-region", "eu-west-1");
+        let mut ignored = cluster(target_env_id, "ignored", "eu-west-1");
+        ignored.ignore = true;
+        let clusters = vec![source.clone(), only.clone(), same_region.clone(), ignored];
+        assert_eq!(
+            target_cluster(&clusters, &source, &target).unwrap(),
+            same_region.id
+        );
+
+        let explicit = CloneDeploymentTarget {
+            cluster_id: Some(only.id),
+            ..target.clone()
+        };
+        assert_eq!(
+            target_cluster(&clusters, &source, &explicit).unwrap(),
+            only.id
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_or_missing_clusters() {
+        let source_env_id = EnvId::from(Uuid::new_v4());
+        let target_env_id = EnvId::from(Uuid::new_v4());
+        let source = cluster(source_env_id, "source", "eu-west-1");
+        let target = CloneDeploymentTarget::new(target_env_id);
+        let error = |clusters: &[K8sCluster], target: &CloneDeploymentTarget| {
+            target_cluster(clusters, &source, target)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error(std::slice::from_ref(&source), &target),
+            "The target env has no clusters"
+        );
+        let clusters = vec![
+            source.clone(),
+            cluster(target_env_id, "a", "us-east-1"),
+            cluster(target_env_id, "b", "us-west-2"),
+        ];
+        assert_eq!(
+            error(&clusters, &target),
+            "The target env has several clusters, set the target cluster_id"
+        );
+        let clusters = vec![
+            source.clone(),
+            cluster(target_env_id, "a", "eu-west-1"),
+            cluster(target_env_id, "b", "eu-west-1"),
+        ];
+        assert_eq!(
+            error(&clusters, &target),
+            "The target env has several clusters, set the target cluster_id"
+        );
+
+        let in_source_env = CloneDeploymentTarget {
+            cluster_id: Some(source.id),
+            ..target.clone()
+        };
+        assert_eq!(
+            error(&clusters, &in_source_env),
+            "Cluster source is not in the target env"
+        );
+        let unknown_cluster_id = K8sClusterId::from(Uuid::new_v4());
+        let unknown = CloneDeploymentTarget {
+            cluster_id: Some(unknown_cluster_id),
+            ..target
+        };
+        assert_eq!(
+            error(&clusters, &unknown),
+            format!("Cluster {unknown_cluster_id} not found")
+        );
+    }
+
+    #[test]
+    fn remaps_secrets() {
+        let source_env_id = EnvId::from(Uuid::new_v4());
+        let target_env_id = EnvId::from(Uuid::new_v4());
+        let source_db = secret(source_env_id, "db", "main");
+        let source_api = secret(source_env_id, "api", "key");
+        let target_db = secret(target_env_id, "db", "main");
+        let target_other = secret(target_env_id, "api", "other");
+        let references = References {
+            target_env_id: Some(target_env_id),
+            source_secrets: vec![source_db.clone(), source_api.clone()],
+            target_secrets: vec![target_other, target_db.clone()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            references.remap_id(&source_db.id.to_string()),
+            Some(Ok(target_db.id.to_string()))
+        );
+        assert_eq!(
+            references.remap_id(&source_api.id.to_string()),
+            Some(Err("no api secret named key".to_owned()))
+        );
+        assert_eq!(references.remap_id(&Uuid::new_v4().to_string()), None);
+        assert_eq!(references.remap_id("not an id"), None);
+    }
+
+    #[test]
+    fn remaps_deployment_resources() {
+        let source_env_id = EnvId::from(Uuid::new_v4());
+        let target_env_id = EnvId::from(Uuid::new_v4());
+        let target_deployment_id = DeploymentId::from(Uuid::new_v4());
+        let source_type = resource_type(Some(source_env_id), "queue");
+        let target_type = resource_type(Some(target_env_id), "queue");
+        let global_type = resource_type(None, "bucket");
+        let unmatched_type = resource_type(Some(source_env_id), "topic");
+
+        let source_queue = resource(&source_type, None, "jobs");
+        let target_queue = resource(&target_type, Some(target_deployment_id), "jobs");
+        let other_env_bucket = resource(
+            &global_type,
+            Some(DeploymentId::from(Uuid::new_v4())),
+            "assets",
+        );
+        let target_bucket = resource(&global_type, Some(target_deployment_id), "assets");
+        let missing_queue = resource(&source_type, None, "events");
+        let topic = resource(&unmatched_type, None, "updates");
+        let references = References {
+            target_env_id: Some(target_env_id),
+            resources: vec![
+                source_queue.clone(),
+                target_queue.clone(),
+                other_env_bucket.clone(),
+                target_bucket.clone(),
+                missing_queue.clone(),
+                topic.clone(),
+            ],
+            resource_types: [source_type, target_type, global_type, unmatched_type]
+                .into_iter()
+                .map(|resource_type| (resource_type.id, resource_type))
+                .collect(),
+            target_deployment_ids: HashSet::from([target_deployment_id]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            references.remap_id(&source_queue.id.to_string()),
+            Some(Ok(target_queue.id.to_string()))
+        );
+        assert_eq!(
+            references.remap_id(&other_env_bucket.id.to_string()),
+            Some(Ok(target_bucket.id.to_string()))
+        );
+        assert_eq!(
+            references.remap_id(&missing_queue.id.to_string()),
+            Some(Err("no queue resource named events".to_owned()))
+        );
+        assert_eq!(
+            references.remap_id(&topic.id.to_string()),
+            Some(Err("no topic resource type".to_owned()))
+        );
+        assert_eq!(references.remap_id(&Uuid::new_v4().to_string()), None);
+    }
+
+    #[test]
+    fn remaps_config_values() {
+        let source_env_id = EnvId::from(Uuid::new_v4());
+        let target_env_id = EnvId::from(Uuid::new_v4());
+        let source_db = secret(source_env_id, "db", "main");
+        let source_api = secret(source_env_id, "api", "key");
+        let target_db = secret(target_env_id, "db", "main");
+        let references = References {
+            target_env_id: Some(target_env_id),
+            source_secrets: vec![source_db.clone(), source_api.clone()],
+            target_secrets: vec![target_db.clone()],
+            ..Default::default()
+        };
+
+        let mut value = json!([source_db.id, source_api.id, "other"]);
+        let mut remapped = Vec::new();
+        let mut unresolved = Vec::new();
+        references.remap_value("secrets", &mut value, &mut remapped, &mut unresolved);
+        assert_eq!(value, json!([target_db.id, source_api.id, "other"]));
+        assert_eq!(remapped.len(), 1);
+        assert_eq!(remapped[0].input_id, "secrets");
+        assert_eq!(unresolved.len(), 1);
+        assert_eq!(unresolved[0].source_id, source_api.id.to_string());
+    }
+}
@@ -0,0 +1,323 @@
+use crate::client::PlatzClient;
+use crate::{DeploymentFilters, DeploymentId, EnvId, K8sCluster, K8sClusterFilters, K8sClusterId};
+use anyhow::Result;
+use async_std::task::sleep;
+use chrono::prelude::*;
+use futures::{Stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use strum::Display;
+
+/// How many unhealthy clusters' deployments are fetched concurrently.
+const DEPLOYMENT_FETCH_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct ClusterHealthOptions {
+    /// Clusters whose agent hasn't been seen for longer than this are
+    /// considered stale.
+    pub stale_after: chrono::Duration,
+}
+
+impl Default for ClusterHealthOptions {
+    fn default() -> Self {
+        Self {
+            stale_after: chrono::Duration::minutes(5),
+        }
+    }
+}
+
+/// The health of a cluster. When more than one applies, the first one in
+/// declaration order is used, so an ignored cluster is never reported as
+/// unhealthy and a stale cluster's `is_ok` isn't trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Display)]
+pub enum ClusterHealthState {
+    Ignored,
+    Stale,
+    NotOk,
+    Healthy,
+    /// The cluster is no longer listed, because it was deleted or no longer
+    /// matches the filters. Only reported by `ClusterHealthWatcher`.
+    Removed,
+}
+
+impl ClusterHealthState {
+    pub fn classify(cluster: &K8sCluster, options: &ClusterHealthOptions) -> Self {
+        if cluster.ignore {
+            Self::Ignored
+        } else if Utc::now() - cluster.last_seen_at > options.stale_after {
+            Self::Stale
+        } else if !cluster.is_ok {
+            Self::NotOk
+        } else {
+            Self::Healthy
+        }
+    }
+
+    /// Whether the cluster needs attention, that is, it's stale or not ok.
+    pub fn is_unhealthy(&self) -> bool {
+        matches!(self, Self::Stale | Self::NotOk)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterHealth {
+    pub cluster_id: K8sClusterId,
+    pub cluster_name: String,
+    pub env_id: Option<EnvId>,
+    pub state: ClusterHealthState,
+    pub not_ok_reason: Option<String>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Deployments on the cluster, only listed for unhealthy clusters.
+    pub affected_deployments: Vec<AffectedDeployment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AffectedDeployment {
+    pub id: DeploymentId,
+    pub name: String,
+    pub status: String,
+}
+
+/// A change in a cluster's health, see `ClusterHealthWatcher`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterHealthTransition {
+    /// The previous state, or `None` if this is the first time the cluster
+    /// was seen by the watcher.
+    pub previous: Option<ClusterHealthState>,
+    pub current: ClusterHealth,
+}
+
+impl PlatzClient {
+    /// Classify clusters by health, listing the deployments on clusters that
+    /// are stale or not ok.
+    pub async fn cluster_health(&self, filters: K8sClusterFilters) -> Result<Vec<ClusterHealth>> {
+        self.cluster_health_with_options(filters, ClusterHealthOptions::default())
+            .await
+    }
+
+    pub async fn cluster_health_with_options(
+        &self,
+        filters: K8sClusterFilters,
+        options: ClusterHealthOptions,
+    ) -> Result<Vec<ClusterHealth>> {
+        let clusters = self.k8s_clusters(filters).await?;
+        futures::stream::iter(clusters.into_iter().map(|cluster| {
+            let state = ClusterHealthState::classify(&cluster, &options);
+            async move {
+                let affected_deployments = if state.is_unhealthy() {
+                    self.deployments(DeploymentFilters {
+                        cluster_id: Some(cluster.id),
+                        ..Default::default()
+                    })
+                    .await?
+                    .into_iter()
+                    .map(|deployment| AffectedDeployment {
+                        id: deployment.id,
+                        name: deployment.name,
+                        status: deployment.status.to_string(),
+                    })
+                    .collect()
+                } else {
+                    Vec::new()
+                };
+                anyhow::Ok(ClusterHealth {
+                    cluster_id: cluster.id,
+                    cluster_name: cluster.name,
+                    env_id: cluster.env_id,
+                    state,
+                    not_ok_reason: cluster.not_ok_reason,
+                    last_seen_at: cluster.last_seen_at,
+                    affected_deployments,
+                })
+            }
+        }))
+        .buffered(DEPLOYMENT_FETCH_CONCURRENCY)
+        .try_collect()
+        .await
+    }
+
+    /// Watch clusters' health, polling every `interval`.
+    pub fn watch_cluster_health(
+        &self,
+        filters: K8sClusterFilters,
+        interval: Duration,
+    ) -> ClusterHealthWatcher<'_> {
+        ClusterHealthWatcher {
+            client: self,
+            filters,
+            options: Default::default(),
+            interval,
+            clusters: HashMap::new(),
+            polled: false,
+            started: false,
+        }
+    }
+}
+
+/// Polls cluster health and reports clusters whose state changed, see
+/// `PlatzClient::watch_cluster_health`. Clusters that are unhealthy when
+/// first seen are reported as transitions without a previous state, healthy
+/// and ignored ones aren't. Clusters that are no longer listed are reported
+/// once with the `Removed` state and their last known health.
+pub struct ClusterHealthWatcher<'a> {
+    client: &'a PlatzClient,
+    filters: K8sClusterFilters,
+    options: ClusterHealthOptions,
+    interval: Duration,
+    clusters: HashMap<K8sClusterId, ClusterHealth>,
+    /// Whether a poll succeeded, so `clusters` holds the previous states.
+    polled: bool,
+    /// Whether `next` polled before, so it waits before polling again.
+    started: bool,
+}
+
+impl<'a> ClusterHealthWatcher<'a> {
+    pub fn with_options(mut self, options: ClusterHealthOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Fetch cluster health now and return the transitions since the last
+    /// successful poll.
+    pub async fn poll(&mut self) -> Result<Vec<ClusterHealthTransition>> {
+        let healths = self
+            .client
+            .cluster_health_with_options(self.filters.clone(), self.options.clone())
+            .await?;
+        let first_poll = !self.polled;
+        self.polled = true;
+        Ok(track_transitions(&mut self.clusters, healths, first_poll))
+    }
+
+    /// Wait for the next transitions, polling every `interval`. The first
+    /// call polls immediately, later calls wait `interval` first, also
+    /// after a failed poll.
+    pub async fn next(&mut self) -> Result<Vec<ClusterHealthTransition>> {
+        loop {
+            if self.started {
+                sleep(self.interval).await;
+            }
+            self.started = true;
+            let transitions = self.poll().await?;
+            if !transitions.is_empty() {
+                return Ok(transitions);
+            }
+        }
+    }
+
+    /// The transitions as a stream that never ends. A failed poll is
+    /// yielded as an error item, and polling continues after `interval`.
+    pub fn into_stream(self) -> impl Stream<Item = Result<ClusterHealthTransition>> + 'a {
+        futures::stream::unfold(self, |mut watcher| async move {
+            let items = match watcher.next().await {
+                Ok(transitions) => transitions.into_iter().map(Ok).collect(),
+                Err(err) => vec![Err(err)],
+            };
+            Some((futures::stream::iter(items), watcher))
+        })
+        .flatten()
+    }
+}
+
+/// Updates `clusters` to `healths` and returns what changed.
+fn track_transitions(
+    clusters: &mut HashMap<K8sClusterId, ClusterHealth>,
+    healths: Vec<ClusterHealth>,
+    first_poll: bool,
+) -> Vec<ClusterHealthTransition> {
+    let mut previous_clusters = std::mem::take(clusters);
+    let mut transitions = Vec::new();
+    for health in healths {
+        let previous = previous_clusters
+            .remove(&health.cluster_id)
+            .map(|previous| previous.state);
+        let changed = match previous {
+            Some(previous) => previous != health.state,
+            None => !first_poll || health.state.is_unhealthy(),
+        };
+        if changed {
+            transitions.push(ClusterHealthTransition {
+                previous,
+                current: health.clone(),
+            });
+        }
+        clusters.insert(health.cluster_id, health);
+    }
+    for (_, mut removed) in previous_clusters {
+        let previous = removed.state;
+        removed.state = ClusterHealthState::Removed;
+        transitions.push(ClusterHealthTransition {
+            previous: Some(previous),
+            current: removed,
+        });
+    }
+    transitions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn health(cluster_id: K8sClusterId, state: ClusterHealthState) -> ClusterHealth {
+        ClusterHealth {
+            cluster_id,
+            cluster_name: cluster_id.to_string(),
+            env_id: None,
+            state,
+            not_ok_reason: None,
+            last_seen_at: Utc::now(),
+            affected_deployments: Vec::new(),
+        }
+    }
+
+    fn states(
+        transitions: &[ClusterHealthTransition],
+    ) -> Vec<(Option<ClusterHealthState>, ClusterHealthState)> {
+        transitions
+            .iter()
+            .map(|transition| (transition.previous, transition.current.state))
+            .collect()
+    }
+
+    #[test]
+    fn tracks_transitions() {
+        use ClusterHealthState::*;
+
+        let (a, b) = (K8sClusterId(Uuid::new_v4()), K8sClusterId(Uuid::new_v4()));
+        let mut clusters = HashMap::new();
+
+        // Only unhealthy clusters are reported on the first poll.
+        let transitions = track_transitions(
+            &mut clusters,
+            vec![health(a, Healthy), health(b, Stale)],
+            true,
+        );
+        assert_eq!(states(&transitions), vec![(None, Stale)]);
+
+        let transitions = track_transitions(
+            &mut clusters,
+            vec![health(a, Healthy), health(b, Stale)],
+            false,
+        );
+        assert!(transitions.is_empty());
+
+        let transitions = track_transitions(
+            &mut clusters,
+            vec![health(a, NotOk), health(b, Healthy)],
+            false,
+        );
+        assert_eq!(
+            states(&transitions),
+            vec![(Some(Healthy), NotOk), (Some(Stale), Healthy)]
+        );
+
+        let transitions = track_transitions(&mut clusters, vec![health(b, Healthy)], false);
+        assert_eq!(states(&transitions), vec![(Some(NotOk), Removed)]);
+        assert_eq!(transitions[0].current.cluster_id, a);
+
+        let transitions = track_transitions(&mut clusters, vec![health(b, Healthy)], false);
+        assert!(transitions.is_empty());
+    }
+}
@@ -1,13 +1,13 @@
 use crate::client::PlatzClient;
+use crate::DeploymentKindId;
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::Deserialize;
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeploymentKind {
-    pub id: Uuid,
+    pub id: DeploymentKindId,
     pub created_at: DateTime<Utc>,
     pub name: String,
 }
@@ -30,7 +30,10 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn deployment_kind(&self, deployment_kind_id: Uuid) -> Result<DeploymentKind> {
+    pub async fn deployment_kind(
+        &self,
+        deployment_kind_id: DeploymentKindId,
+    ) -> Result<DeploymentKind> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -0,0 +1,77 @@
+use crate::client::PlatzClient;
+use crate::{DeploymentId, K8sResourceFilters};
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::Serialize;
+use std::ops::Range;
+use url::Url;
+
+/// Where to find a deployment and its logs, see
+/// `PlatzClient::deployment_links`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DeploymentLinks {
+    pub deployment_id: DeploymentId,
+    pub namespace: String,
+    /// The URL under the cluster's ingress domain, followed by the hosts of
+    /// the deployment's Ingress resources.
+    pub public_urls: Vec<Url>,
+    /// Logs of the deployment's namespace in Grafana Explore, if the
+    /// cluster has a Grafana URL. It opens in the user's current Grafana
+    /// organization.
+    pub grafana_explore_url: Option<Url>,
+    /// Why the hosts of some of the deployment's Ingress resources couldn't
+    /// be read, for example because only their ObjectMeta is stored, in
+    /// which case `public_urls` may be incomplete.
+    pub resource_errors: Vec<String>,
+}
+
+impl PlatzClient {
+    /// Links to a deployment's public URLs and its logs during `window`.
+    pub async fn deployment_links(
+        &self,
+        deployment_id: DeploymentId,
+        window: Range<DateTime<Utc>>,
+    ) -> Result<DeploymentLinks> {
+        let deployment = self.deployment(deployment_id).await?;
+        let (kind, cluster, resources) = futures::try_join!(
+            self.deployment_kind(deployment.kind_id),
+            self.k8s_cluster(deployment.cluster_id),
+            self.typed_k8s_resources(K8sResourceFilters {
+                deployment_id: Some(deployment_id),
+                ..Default::default()
+            }),
+        )?;
+        let namespace = deployment.namespace_name(&kind);
+
+        let mut public_urls: Vec<Url> = cluster.ingress_url(&namespace).into_iter().collect();
+        let scheme = public_urls
+            .first()
+            .map_or("https", |url| url.scheme())
+            .to_owned();
+        let mut resource_errors = Vec::new();
+        for resource in resources.iter() {
+            let hosts = match resource.ingress_hosts() {
+                Ok(hosts) => hosts,
+                Err(err) => {
+                    resource_errors.push(err.to_string());
+                    continue;
+                }
+            };
+            for host in hosts {
+                if let Ok(url) = Url::parse(&format!("{scheme}://{host}/"))
+                    && !public_urls.contains(&url)
+                {
+                    public_urls.push(url);
+                }
+            }
+        }
+
+        Ok(DeploymentLinks {
+            deployment_id,
+            grafana_explore_url: cluster.grafana_explore_url(&namespace, window, None),
+            namespace,
+            public_urls,
+            resource_errors,
+        })
+    }
+}
@@ -1,16 +1,15 @@
-use crate::{PlatzClient, PlatzRequest};
+use crate::{DeploymentKindId, DeploymentResourceTypeId, EnvId, PlatzClient, PlatzRequest};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{IntoVec, prelude::*};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct DeploymentResourceType {
-    pub id: Uuid,
+    pub id: DeploymentResourceTypeId,
     pub created_at: DateTime<Utc>,
-    pub env_id: Option<Uuid>,
-    pub deployment_kind_id: Uuid,
+    pub env_id: Option<EnvId>,
+    pub deployment_kind_id: DeploymentKindId,
     pub key: String,
     pub spec: serde_json::Value,
 }
@@ -18,9 +17,9 @@ pub struct DeploymentResourceType {
 #[derive(Default, IntoVec)]
 pub struct DeploymentResourceTypeFilters {
     #[kv(optional)]
-    pub env_id: Option<Uuid>,
+    pub env_id: Option<EnvId>,
     #[kv(optional)]
-    pub deployment_kind_id: Option<Uuid>,
+    pub deployment_kind_id: Option<DeploymentKindId>,
     #[kv(optional)]
     pub key: Option<String>,
 }
@@ -46,7 +45,7 @@ impl<'s> PlatzClient {
 
     pub async fn deployment_resource_type(
         &self,
-        deployment_resource_type_id: Uuid,
+        deployment_resource_type_id: DeploymentResourceTypeId,
     ) -> Result<DeploymentResourceType> {
         Ok(self
             .request(
@@ -59,7 +58,7 @@ impl<'s> PlatzClient {
 
     pub async fn find_global_deployment_resource_type(
         self,
-        deployment_kind_id: Uuid,
+        deployment_kind_id: DeploymentKindId,
         key: String,
     ) -> Result<DeploymentResourceType> {
         Ok(self
@@ -74,8 +73,8 @@ impl<'s> PlatzClient {
 
     pub async fn find_deployment_resource_type(
         self,
-        env_id: Uuid,
-        deployment_kind_id: Uuid,
+        env_id: EnvId,
+        deployment_kind_id: DeploymentKindId,
         key: String,
     ) -> Result<DeploymentResourceType> {
         Ok(self
@@ -1,16 +1,15 @@
-use crate::PlatzClient;
+use crate::{DeploymentId, DeploymentResourceId, DeploymentResourceTypeId, PlatzClient};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{IntoVec, prelude::*};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DeploymentResource {
-    pub id: Uuid,
+    pub id: DeploymentResourceId,
     pub created_at: DateTime<Utc>,
-    pub type_id: Uuid,
-    pub deployment_id: Option<Uuid>,
+    pub type_id: DeploymentResourceTypeId,
+    pub deployment_id: Option<DeploymentId>,
     pub name: String,
     pub exists: bool,
     pub props: serde_json::Value,
@@ -26,10 +25,10 @@ pub struct UpdateDeploymentResource {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct NewDeploymentResource {
-    pub id: Option<Uuid>,
+    pub id: Option<DeploymentResourceId>,
     pub created_at: Option<DateTime<Utc>>,
-    pub type_id: Uuid,
-    pub deployment_id: Uuid,
+    pub type_id: DeploymentResourceTypeId,
+    pub deployment_id: DeploymentId,
     pub name: String,
     pub props: serde_json::Value,
     pub sync_status: Option<SyncStatus>,
@@ -38,7 +37,7 @@ pub struct NewDeploymentResource {
 #[derive(Default, IntoVec)]
 pub struct DeploymentResourceFilters {
     #[kv(optional)]
-    pub type_id: Option<Uuid>,
+    pub type_id: Option<DeploymentResourceTypeId>,
 }
 
 impl PlatzClient {
@@ -54,7 +53,7 @@ impl PlatzClient {
     }
     pub async fn deployment_resource(
         &self,
-        deployment_resource_id: Uuid,
+        deployment_resource_id: DeploymentResourceId,
     ) -> Result<DeploymentResource> {
         Ok(self
             .request(
@@ -77,7 +76,7 @@ impl PlatzClient {
 
     pub async fn update_deployment_resource(
         &self,
-        deployment_resource_id: Uuid,
+        deployment_resource_id: DeploymentResourceId,
         update_deployment_resource: UpdateDeploymentResource,
     ) -> Result<DeploymentResource> {
         Ok(self
@@ -98,4 +97,27 @@ pub enum SyncStatus {
     Deleting,
     Ready,
     Error,
+    /// A status not known to this version of the SDK.
+    #[serde(untagged)]
+    Other(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deserializes_sync_statuses() {
+        let status = |value: &str| -> SyncStatus { serde_json::from_value(json!(value)).unwrap() };
+        assert!(matches!(status("Ready"), SyncStatus::Ready));
+        assert!(matches!(status("Deleting"), SyncStatus::Deleting));
+        let other = status("Orphaned");
+        assert!(matches!(&other, SyncStatus::Other(name) if name == "Orphaned"));
+        assert_eq!(serde_json::to_value(&other).unwrap(), json!("Orphaned"));
+        assert_eq!(
+            serde_json::to_value(SyncStatus::Ready).unwrap(),
+            json!("Ready")
+        );
+    }
 }
@@ -1,11 +1,15 @@
 use crate::client::{Paginated, PlatzClient};
-use anyhow::Result;
+use crate::{
+    json_diff, Deployment, DeploymentId, DeploymentTaskId, HelmChartId, JsonDiff, K8sClusterId,
+    K8sResource, K8sResourceId, UpdateDeployment, UserId,
+};
+use anyhow::{bail, Result};
+use async_std::task::sleep;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
-use uuid::Uuid;
+use std::collections::HashSet;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Clone)]
 pub enum DeploymentTaskStatus {
@@ -14,15 +18,17 @@ pub enum DeploymentTaskStatus {
     Failed,
     Canceled,
     Done,
+    /// A status not known to this version of the SDK.
+    #[serde(untagged)]
+    Other(String),
 }
 
-pub type JsonDiff = HashMap<String, (Value, Value)>;
 #[derive(Default, IntoVec)]
 pub struct DeploymentTaskFilters {
     #[kv(optional)]
-    pub cluster_id: Option<Uuid>,
+    pub cluster_id: Option<K8sClusterId>,
     #[kv(optional)]
-    pub deployment_id: Option<Uuid>,
+    pub deployment_id: Option<DeploymentId>,
     #[kv(optional)]
     pub active_only: Option<bool>,
     #[kv(optional)]
@@ -33,28 +39,36 @@ pub struct DeploymentTaskFilters {
 
 #[derive(Debug, Serialize)]
 pub struct ApiNewDeploymentTask {
-    pub deployment_id: Uuid,
+    pub deployment_id: DeploymentId,
     pub operation: DeploymentTaskOperation,
     pub execute_at: Option<DateTime<Utc>>,
 }
 
+impl DeploymentTaskStatus {
+    /// Whether the task won't change anymore: it's done, failed or was
+    /// canceled.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Failed | Self::Canceled | Self::Done)
+    }
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct DeploymentTask {
-    pub id: Uuid,
+    pub id: DeploymentTaskId,
     pub created_at: DateTime<Utc>,
     pub execute_at: DateTime<Utc>,
     pub first_attempted_at: Option<DateTime<Utc>>,
     pub started_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
-    pub cluster_id: Uuid,
-    pub deployment_id: Uuid,
-    pub acting_user_id: Option<Uuid>,
-    pub acting_deployment_id: Option<Uuid>,
+    pub cluster_id: K8sClusterId,
+    pub deployment_id: DeploymentId,
+    pub acting_user_id: Option<UserId>,
+    pub acting_deployment_id: Option<DeploymentId>,
     pub operation: DeploymentTaskOperation,
     pub status: DeploymentTaskStatus,
     pub reason: Option<String>,
-    pub canceled_by_user_id: Option<Uuid>,
-    pub canceled_by_deployment_id: Option<Uuid>,
+    pub canceled_by_user_id: Option<UserId>,
+    pub canceled_by_deployment_id: Option<DeploymentId>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -62,10 +76,14 @@ pub enum DeploymentTaskOperation {
     Install(DeploymentInstallTask),
     Upgrade(DeploymentUpgradeTask),
     Reinstall(DeploymentReinstallTask),
-    Recreate(DeploymentRecreaseTask),
+    Recreate(DeploymentRecreateTask),
     Uninstall(DeploymentUninstallTask),
     InvokeAction(DeploymentInvokeActionTask),
     RestartK8sResource(DeploymentRestartK8sResourceTask),
+    /// An operation not known to this version of the SDK, kept as the raw
+    /// JSON sent by the server.
+    #[serde(untagged)]
+    Other(serde_json::Value),
 }
 
 impl DeploymentTaskOperation {
@@ -78,21 +96,26 @@ impl DeploymentTaskOperation {
             Self::Uninstall(_) => "Uninstall".into(),
             Self::InvokeAction(x) => format!("Invoke Action {}", x.action_id),
             Self::RestartK8sResource(_) => "Restart K8s Resource".into(),
+            Self::Other(value) => value
+                .as_object()
+                .and_then(|operation| operation.keys().next())
+                .cloned()
+                .unwrap_or_else(|| "Unknown".into()),
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentInstallTask {
-    pub helm_chart_id: Uuid,
+    pub helm_chart_id: HelmChartId,
     pub config_inputs: serde_json::Value,
     pub values_override: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentUpgradeTask {
-    pub helm_chart_id: Uuid,
-    pub prev_helm_chart_id: Option<Uuid>,
+    pub helm_chart_id: HelmChartId,
+    pub prev_helm_chart_id: Option<HelmChartId>,
     pub config_inputs: serde_json::Value,
     pub config_delta: Option<JsonDiff>,
     pub values_override: Option<serde_json::Value>,
@@ -102,30 +125,102 @@ pub struct DeploymentUpgradeTask {
 pub struct DeploymentReinstallTask {
     pub reason: String,
 }
+
+/// Moves a deployment to another cluster or namespace, see
+/// `PlatzClient::move_deployment`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DeploymentRecreaseTask {
-    pub old_cluster_id: Uuid,
+pub struct DeploymentRecreateTask {
+    pub old_cluster_id: K8sClusterId,
     pub old_namespace: String,
-    pub new_cluster_id: Uuid,
+    pub new_cluster_id: K8sClusterId,
     pub new_namespace: String,
 }
 
+#[deprecated(note = "renamed to DeploymentRecreateTask")]
+pub type DeploymentRecreaseTask = DeploymentRecreateTask;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentUninstallTask {}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentInvokeActionTask {
-    pub helm_chart_id: Uuid,
+    pub helm_chart_id: HelmChartId,
     pub action_id: String,
     pub body: serde_json::Value,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeploymentRestartK8sResourceTask {
-    pub resource_id: Uuid,
+    pub resource_id: K8sResourceId,
     pub resource_name: String,
 }
 
+impl From<&K8sResource> for DeploymentRestartK8sResourceTask {
+    fn from(resource: &K8sResource) -> Self {
+        Self {
+            resource_id: resource.id,
+            resource_name: resource.name.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveDeploymentOptions {
+    /// How often to check whether the Recreate task has finished.
+    pub poll_interval: Duration,
+    /// How long to wait for the Recreate task to be created and finish.
+    pub timeout: Duration,
+}
+
+impl Default for MoveDeploymentOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(15 * 60),
+        }
+    }
+}
+
+/// The result of `PlatzClient::move_deployment`.
+#[derive(Debug, Clone)]
+pub struct MovedDeployment {
+    pub deployment: Deployment,
+    pub task: DeploymentTask,
+    pub recreate: DeploymentRecreateTask,
+}
+
+/// Upgrade `deployment` to `helm_chart_id` with `config`, or its current
+/// config if not given. The delta is computed against the current config.
+fn upgrade_task(
+    deployment: Deployment,
+    helm_chart_id: HelmChartId,
+    config: Option<serde_json::Value>,
+) -> DeploymentUpgradeTask {
+    let config_inputs = config.unwrap_or_else(|| deployment.config.clone());
+    DeploymentUpgradeTask {
+        helm_chart_id,
+        prev_helm_chart_id: Some(deployment.helm_chart_id),
+        config_delta: Some(json_diff(&deployment.config, &config_inputs)),
+        config_inputs,
+        values_override: deployment.values_override,
+    }
+}
+
+/// The first Recreate task among `tasks` that isn't one of the
+/// `existing_task_ids` seen before the move.
+fn find_recreate_task(
+    tasks: Vec<DeploymentTask>,
+    existing_task_ids: &HashSet<DeploymentTaskId>,
+) -> Option<(DeploymentRecreateTask, DeploymentTask)> {
+    tasks
+        .into_iter()
+        .filter(|task| !existing_task_ids.contains(&task.id))
+        .find_map(|task| match &task.operation {
+            DeploymentTaskOperation::Recreate(recreate) => Some((recreate.clone(), task)),
+            _ => None,
+        })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CancelDeploymentTask {
     pub reason: Option<String>,
@@ -143,7 +238,10 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn deployment_task(&self, deployment_task_id: Uuid) -> Result<DeploymentTask> {
+    pub async fn deployment_task(
+        &self,
+        deployment_task_id: DeploymentTaskId,
+    ) -> Result<DeploymentTask> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -153,7 +251,23 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn last_deployment_task(&self, deployment_id: Uuid) -> Result<DeploymentTask> {
+    /// The deployment's most recent task. Fails if it has no tasks, see
+    /// `latest_deployment_task`.
+    pub async fn last_deployment_task(
+        &self,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentTask> {
+        match self.latest_deployment_task(deployment_id).await? {
+            Some(task) => Ok(task),
+            None => bail!("Deployment {deployment_id} has no tasks"),
+        }
+    }
+
+    /// The deployment's most recent task, if it has any.
+    pub async fn latest_deployment_task(
+        &self,
+        deployment_id: DeploymentId,
+    ) -> Result<Option<DeploymentTask>> {
         let single_page_tasks: Paginated<DeploymentTask> = self
             .request(reqwest::Method::GET, "/api/v2/deployment-tasks")
             .add_to_query(
@@ -165,12 +279,12 @@ impl PlatzClient {
             )
             .single_page(1, Some(1))
             .await?;
-        Ok(single_page_tasks.items.first().unwrap().to_owned())
+        Ok(single_page_tasks.items.into_iter().next())
     }
 
     pub async fn cancel_deployment_task(
         &self,
-        deployment_task_id: Uuid,
+        deployment_task_id: DeploymentTaskId,
         info: CancelDeploymentTask,
     ) -> Result<DeploymentTask> {
         Ok(self
@@ -192,3 +306,488 @@ impl PlatzClient {
             .await?)
     }
 }
+
+impl PlatzClient {
+    async fn new_deployment_task(
+        &self,
+        deployment_id: DeploymentId,
+        operation: DeploymentTaskOperation,
+        execute_at: Option<DateTime<Utc>>,
+    ) -> Result<DeploymentTask> {
+        self.create_deployment_task(ApiNewDeploymentTask {
+            deployment_id,
+            operation,
+            execute_at,
+        })
+        .await
+    }
+
+    async fn upgrade_operation(
+        &self,
+        deployment_id: DeploymentId,
+        helm_chart_id: HelmChartId,
+        config: Option<serde_json::Value>,
+    ) -> Result<DeploymentTaskOperation> {
+        let deployment = self.deployment(deployment_id).await?;
+        Ok(DeploymentTaskOperation::Upgrade(upgrade_task(
+            deployment,
+            helm_chart_id,
+            config,
+        )))
+    }
+
+    /// Upgrade a deployment to another helm chart, keeping its current config.
+    pub async fn upgrade_deployment(
+        &self,
+        deployment_id: DeploymentId,
+        helm_chart_id: HelmChartId,
+    ) -> Result<DeploymentTask> {
+        self.schedule_upgrade_deployment_with_config(deployment_id, helm_chart_id, None, None)
+            .await
+    }
+
+    /// Upgrade a deployment to another helm chart with a new config. The
+    /// config delta is computed against the deployment's current config.
+    pub async fn upgrade_deployment_with_config(
+        &self,
+        deployment_id: DeploymentId,
+        helm_chart_id: HelmChartId,
+        config: serde_json::Value,
+    ) -> Result<DeploymentTask> {
+        self.schedule_upgrade_deployment_with_config(
+            deployment_id,
+            helm_chart_id,
+            Some(config),
+            None,
+        )
+        .await
+    }
+
+    pub async fn schedule_upgrade_deployment(
+        &self,
+        deployment_id: DeploymentId,
+        helm_chart_id: HelmChartId,
+        execute_at: DateTime<Utc>,
+    ) -> Result<DeploymentTask> {
+        self.schedule_upgrade_deployment_with_config(
+            deployment_id,
+            helm_chart_id,
+            None,
+            Some(execute_at),
+        )
+        .await
+    }
+
+    pub async fn schedule_upgrade_deployment_with_config(
+        &self,
+        deployment_id: DeploymentId,
+        helm_chart_id: HelmChartId,
+        config: Option<serde_json::Value>,
+        execute_at: Option<DateTime<Utc>>,
+    ) -> Result<DeploymentTask> {
+        let operation = self
+            .upgrade_operation(deployment_id, helm_chart_id, config)
+            .await?;
+        self.new_deployment_task(deployment_id, operation, execute_at)
+            .await
+    }
+
+    pub async fn reinstall_deployment<S>(
+        &self,
+        deployment_id: DeploymentId,
+        reason: S,
+    ) -> Result<DeploymentTask>
+    where
+        S: Into<String>,
+    {
+        self.new_deployment_task(
+            deployment_id,
+            DeploymentTaskOperation::Reinstall(DeploymentReinstallTask {
+                reason: reason.into(),
+            }),
+            None,
+        )
+        .await
+    }
+
+    pub async fn schedule_reinstall_deployment<S>(
+        &self,
+        deployment_id: DeploymentId,
+        reason: S,
+        execute_at: DateTime<Utc>,
+    ) -> Result<DeploymentTask>
+    where
+        S: Into<String>,
+    {
+        self.new_deployment_task(
+            deployment_id,
+            DeploymentTaskOperation::Reinstall(DeploymentReinstallTask {
+                reason: reason.into(),
+            }),
+            Some(execute_at),
+        )
+        .await
+    }
+
+    pub async fn uninstall_deployment(
+        &self,
+        deployment_id: DeploymentId,
+    ) -> Result<DeploymentTask> {
+        self.new_deployment_task(
+            deployment_id,
+            DeploymentTaskOperation::Uninstall(DeploymentUninstallTask {}),
+            None,
+        )
+        .await
+    }
+
+    pub async fn schedule_uninstall_deployment(
+        &self,
+        deployment_id: DeploymentId,
+        execute_at: DateTime<Utc>,
+    ) -> Result<DeploymentTask> {
+        self.new_deployment_task(
+            deployment_id,
+            DeploymentTaskOperation::Uninstall(DeploymentUninstallTask {}),
+            Some(execute_at),
+        )
+        .await
+    }
+
+    /// Move a deployment to another cluster in the same env. Both clusters
+    /// have to belong to an env, and the target cluster has to be ok. Platz
+    /// recreates the deployment on the new cluster, and this waits for that
+    /// Recreate task to finish. Disabled deployments aren't recreated, so
+    /// they can't be moved this way.
+    pub async fn move_deployment(
+        &self,
+        deployment_id: DeploymentId,
+        new_cluster_id: K8sClusterId,
+    ) -> Result<MovedDeployment> {
+        self.move_deployment_with_options(
+            deployment_id,
+            new_cluster_id,
+            MoveDeploymentOptions::default(),
+        )
+        .await
+    }
+
+    pub async fn move_deployment_with_options(
+        &self,
+        deployment_id: DeploymentId,
+        new_cluster_id: K8sClusterId,
+        options: MoveDeploymentOptions,
+    ) -> Result<MovedDeployment> {
+        let deployment = self.deployment(deployment_id).await?;
+        // Platz only recreates enabled deployments, so there would be no
+        // Recreate task to wait for.
+        if !deployment.enabled {
+            bail!(
+                "Deployment {} is disabled, update its cluster instead of moving it",
+                deployment.name
+            );
+        }
+        if deployment.cluster_id == new_cluster_id {
+            bail!(
+                "Deployment {} is already on cluster {new_cluster_id}",
+                deployment.name
+            );
+        }
+        let (old_cluster, new_cluster) = futures::try_join!(
+            self.k8s_cluster(deployment.cluster_id),
+            self.k8s_cluster(new_cluster_id),
+        )?;
+        // Clusters without an env aren't in the same env as anything.
+        match (old_cluster.env_id, new_cluster.env_id) {
+            (Some(old_env_id), Some(new_env_id)) if old_env_id == new_env_id => {}
+            (None, _) => bail!("Cluster {} is not in any env", old_cluster.name),
+            (_, None) => bail!("Cluster {} is not in any env", new_cluster.name),
+            _ => bail!(
+                "Cluster {} is not in the same env as cluster {}",
+                new_cluster.name,
+                old_cluster.name
+            ),
+        }
+        if !new_cluster.is_ok {
+            bail!(
+                "Cluster {} is not ok: {}",
+                new_cluster.name,
+                new_cluster
+                    .not_ok_reason
+                    .as_deref()
+                    .unwrap_or("no reason given")
+            );
+        }
+
+        // Tasks created since a while before the move that already exist
+        // aren't the Recreate task, even if the server's clock is behind.
+        let created_from = Utc::now() - chrono::Duration::minutes(5);
+        let existing_task_ids: HashSet<DeploymentTaskId> = self
+            .deployment_tasks(DeploymentTaskFilters {
+                deployment_id: Some(deployment_id),
+                created_from: Some(created_from),
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+
+        let deployment = self
+            .update_deployment(
+                deployment_id,
+                UpdateDeployment {
+                    cluster_id: Some(new_cluster_id),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let deadline = std::time::Instant::now() + options.timeout;
+        loop {
+            let recreate_task = find_recreate_task(
+                self.deployment_tasks(DeploymentTaskFilters {
+                    deployment_id: Some(deployment_id),
+                    created_from: Some(created_from),
+                    ..Default::default()
+                })
+                .await?,
+                &existing_task_ids,
+            );
+            if let Some((recreate, task)) = recreate_task
+                && task.status.is_finished()
+            {
+                if !matches!(task.status, DeploymentTaskStatus::Done) {
+                    bail!(
+                        "Recreate task of deployment {} {:?}: {}",
+                        deployment.name,
+                        task.status,
+                        task.reason.as_deref().unwrap_or("no reason given")
+                    );
+                }
+                return Ok(MovedDeployment {
+                    deployment: self.deployment(deployment_id).await?,
+                    task,
+                    recreate,
+                });
+            }
+            if std::time::Instant::now() >= deadline {
+                bail!(
+                    "Timed out waiting for the Recreate task of deployment {}",
+                    deployment.name
+                );
+            }
+            sleep(options.poll_interval).await;
+        }
+    }
+
+    pub async fn restart_k8s_resource(&self, resource: &K8sResource) -> Result<DeploymentTask> {
+        self.new_deployment_task(
+            resource.deployment_id,
+            DeploymentTaskOperation::RestartK8sResource(resource.into()),
+            None,
+        )
+        .await
+    }
+
+    pub async fn schedule_restart_k8s_resource(
+        &self,
+        resource: &K8sResource,
+        execute_at: DateTime<Utc>,
+    ) -> Result<DeploymentTask> {
+        self.new_deployment_task(
+            resource.deployment_id,
+            DeploymentTaskOperation::RestartK8sResource(resource.into()),
+            Some(execute_at),
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn task(operation: serde_json::Value) -> DeploymentTask {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "execute_at": "2024-01-01T00:00:00Z",
+            "first_attempted_at": null,
+            "started_at": null,
+            "finished_at": null,
+            "cluster_id": Uuid::new_v4(),
+            "deployment_id": Uuid::new_v4(),
+            "acting_user_id": null,
+            "acting_deployment_id": null,
+            "operation": operation,
+            "status": "Pending",
+            "reason": null,
+            "canceled_by_user_id": null,
+            "canceled_by_deployment_id": null,
+        }))
+        .unwrap()
+    }
+
+    fn recreate() -> DeploymentTask {
+        task(json!({
+            "Recreate": {
+                "old_cluster_id": Uuid::new_v4(),
+                "old_namespace": "old",
+                "new_cluster_id": Uuid::new_v4(),
+                "new_namespace": "new",
+            }
+        }))
+    }
+
+    fn deployment(config: serde_json::Value) -> Deployment {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "name": "web",
+            "kind_id": Uuid::new_v4(),
+            "cluster_id": Uuid::new_v4(),
+            "enabled": true,
+            "status": "Running",
+            "description_md": null,
+            "reason": null,
+            "revision_id": null,
+            "reported_status": null,
+            "helm_chart_id": Uuid::new_v4(),
+            "config": config,
+            "values_override": {"replicas": 2},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn computes_upgrade_config_delta() {
+        let deployment = deployment(json!({
+            "image": {"tag": "1.0", "pull_policy": "Always"},
+            "hosts": ["a.example.com"],
+        }));
+        let helm_chart_id = HelmChartId::from(Uuid::new_v4());
+
+        let upgrade = upgrade_task(
+            deployment.clone(),
+            helm_chart_id,
+            Some(json!({
+                "image": {"tag": "1.1", "pull_policy": "Always"},
+                "hosts": ["a.example.com", "b.example.com"],
+                "debug": true,
+            })),
+        );
+        assert_eq!(
+            upgrade.config_delta.unwrap(),
+            JsonDiff::from([
+                ("image.tag".to_owned(), (json!("1.0"), json!("1.1"))),
+                (
+                    "hosts".to_owned(),
+                    (
+                        json!(["a.example.com"]),
+                        json!(["a.example.com", "b.example.com"])
+                    )
+                ),
+                ("debug".to_owned(), (json!(null), json!(true))),
+            ])
+        );
+
+        // Without a new config the current one is kept, with no changes.
+        let upgrade = upgrade_task(deployment.clone(), helm_chart_id, None);
+        assert_eq!(upgrade.config_inputs, deployment.config);
+        assert_eq!(upgrade.config_delta, Some(JsonDiff::new()));
+    }
+
+    #[test]
+    fn builds_upgrade_payload() {
+        let deployment = deployment(json!({"image": {"tag": "1.0"}}));
+        let helm_chart_id = HelmChartId::from(Uuid::new_v4());
+        let new_task = ApiNewDeploymentTask {
+            deployment_id: deployment.id,
+            operation: DeploymentTaskOperation::Upgrade(upgrade_task(
+                deployment.clone(),
+                helm_chart_id,
+                Some(json!({"image": {"tag": "1.1"}})),
+            )),
+            execute_at: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&new_task).unwrap(),
+            json!({
+                "deployment_id": deployment.id,
+                "operation": {
+                    "Upgrade": {
+                        "helm_chart_id": helm_chart_id,
+                        "prev_helm_chart_id": deployment.helm_chart_id,
+                        "config_inputs": {"image": {"tag": "1.1"}},
+                        "config_delta": {"image.tag": ["1.0", "1.1"]},
+                        "values_override": {"replicas": 2},
+                    }
+                },
+                "execute_at": null,
+            })
+        );
+    }
+
+    #[test]
+    fn deserializes_task_statuses() {
+        let status =
+            |value: &str| -> DeploymentTaskStatus { serde_json::from_value(json!(value)).unwrap() };
+        assert!(matches!(status("Done"), DeploymentTaskStatus::Done));
+        assert!(matches!(status("Canceled"), DeploymentTaskStatus::Canceled));
+        let other = status("Paused");
+        assert!(matches!(&other, DeploymentTaskStatus::Other(name) if name == "Paused"));
+        assert!(!other.is_finished());
+    }
+
+    #[test]
+    fn deserializes_task_operations() {
+        let reinstall = task(json!({"Reinstall": {"reason": "test"}}));
+        assert!(matches!(
+            &reinstall.operation,
+            DeploymentTaskOperation::Reinstall(reinstall) if reinstall.reason == "test"
+        ));
+        assert!(matches!(
+            recreate().operation,
+            DeploymentTaskOperation::Recreate(_)
+        ));
+
+        let unknown = json!({"Snapshot": {"volume": "data"}});
+        let snapshot = task(unknown.clone());
+        assert!(matches!(
+            &snapshot.operation,
+            DeploymentTaskOperation::Other(value) if *value == unknown
+        ));
+        assert_eq!(snapshot.operation.get_type_name(), "Snapshot");
+        // Unknown operations are sent back to the server as they came.
+        assert_eq!(serde_json::to_value(&snapshot.operation).unwrap(), unknown);
+    }
+
+    #[test]
+    fn finds_new_recreate_task() {
+        let existing = recreate();
+        let reinstall = task(json!({"Reinstall": {"reason": "test"}}));
+        let new = recreate();
+        let existing_task_ids = HashSet::from([existing.id]);
+
+        let (recreate, task) =
+            find_recreate_task(vec![existing, reinstall, new.clone()], &existing_task_ids).unwrap();
+        assert_eq!(task.id, new.id);
+        assert_eq!(recreate.new_namespace, "new");
+    }
+
+    #[test]
+    fn ignores_existing_and_other_tasks() {
+        let existing = recreate();
+        let existing_task_ids = HashSet::from([existing.id]);
+        let tasks = vec![
+            existing,
+            task(json!({"Uninstall": {}})),
+            task(json!({"Reinstall": {"reason": "test"}})),
+        ];
+        assert!(find_recreate_task(tasks, &existing_task_ids).is_none());
+        assert!(find_recreate_task(vec![], &HashSet::new()).is_none());
+    }
+}
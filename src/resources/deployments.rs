@@ -1,29 +1,111 @@
 use crate::client::PlatzClient;
+use crate::{
+    DeploymentId, DeploymentKind, DeploymentKindId, DeploymentRevisionId, EnvId, HelmChartId,
+    K8sClusterId, PlatzStatus,
+};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
 use strum::Display;
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Deployment {
-    pub id: Uuid,
+    pub id: DeploymentId,
     pub created_at: DateTime<Utc>,
     pub name: String,
-    pub kind_id: Uuid,
-    pub cluster_id: Uuid,
+    pub kind_id: DeploymentKindId,
+    pub cluster_id: K8sClusterId,
     pub enabled: bool,
     pub status: DeploymentStatus,
     pub description_md: Option<String>,
     pub reason: Option<String>,
-    pub revision_id: Option<Uuid>,
+    pub revision_id: Option<DeploymentRevisionId>,
     pub reported_status: Option<serde_json::Value>,
-    pub helm_chart_id: Uuid,
+    pub helm_chart_id: HelmChartId,
     pub config: serde_json::Value,
     pub values_override: Option<serde_json::Value>,
 }
 
+/// A deployment's status as last polled by Platz from its status endpoint,
+/// see `Deployment::reported_status_typed`.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+enum RawReportedStatus {
+    Polled {
+        timestamp: DateTime<Utc>,
+        get_successful: bool,
+        content: Option<PlatzStatus<String>>,
+    },
+    Content(PlatzStatus<String>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ReportedStatus {
+    /// When Platz last polled the status endpoint, if known.
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Whether the last poll succeeded. When it didn't, `status` holds the
+    /// last status that was successfully read, if any.
+    pub get_successful: bool,
+    pub status: Option<PlatzStatus<String>>,
+}
+
+impl ReportedStatus {
+    /// Time since the status was last polled.
+    pub fn age(&self) -> Option<chrono::Duration> {
+        self.timestamp.map(|timestamp| Utc::now() - timestamp)
+    }
+
+    /// Checks whether the status is older than `max_age` or the last poll
+    /// failed. A status whose age is unknown isn't known to be stale.
+    pub fn is_stale(&self, max_age: chrono::Duration) -> bool {
+        !self.get_successful || self.age().is_some_and(|age| age > max_age)
+    }
+}
+
+impl Deployment {
+    /// The K8s namespace Platz installs the deployment in, `<kind>-<name>`,
+    /// or just the kind name for deployments without a name. It's also used
+    /// as the deployment's ingress host name.
+    pub fn namespace_name(&self, kind: &DeploymentKind) -> String {
+        if self.name.is_empty() {
+            kind.name.to_lowercase()
+        } else {
+            format!("{}-{}", kind.name, self.name).to_lowercase()
+        }
+    }
+
+    /// Parse `reported_status` into the types deployments report it with.
+    /// Returns `None` if Platz hasn't read the deployment's status yet, or
+    /// the deployment kind doesn't have a status endpoint.
+    pub fn reported_status_typed(&self) -> Result<Option<ReportedStatus>> {
+        let Some(reported_status) = self.reported_status.as_ref() else {
+            return Ok(None);
+        };
+        if reported_status.is_null() {
+            return Ok(None);
+        }
+        Ok(Some(
+            match RawReportedStatus::deserialize(reported_status)? {
+                RawReportedStatus::Polled {
+                    timestamp,
+                    get_successful,
+                    content,
+                } => ReportedStatus {
+                    timestamp: Some(timestamp),
+                    get_successful,
+                    status: content,
+                },
+                RawReportedStatus::Content(status) => ReportedStatus {
+                    timestamp: None,
+                    get_successful: true,
+                    status: Some(status),
+                },
+            },
+        ))
+    }
+}
+
 #[derive(Debug, Deserialize, Clone, Display)]
 pub enum DeploymentStatus {
     Unknown,
@@ -35,15 +117,19 @@ pub enum DeploymentStatus {
     Uninstalling,
     Uninstalled,
     Deleting,
+    /// A status not known to this version of the SDK.
+    #[serde(untagged)]
+    #[strum(transparent)]
+    Other(String),
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct NewDeployment {
     #[serde(default)]
     pub name: String,
-    pub kind_id: Uuid,
-    pub cluster_id: Uuid,
-    pub helm_chart_id: Uuid,
+    pub kind_id: DeploymentKindId,
+    pub cluster_id: K8sClusterId,
+    pub helm_chart_id: HelmChartId,
     pub config: Option<serde_json::Value>,
     pub values_override: Option<serde_json::Value>,
 }
@@ -51,8 +137,8 @@ pub struct NewDeployment {
 #[derive(Debug, Serialize, Default)]
 pub struct UpdateDeployment {
     pub name: Option<String>,
-    pub cluster_id: Option<Uuid>,
-    pub helm_chart_id: Option<Uuid>,
+    pub cluster_id: Option<K8sClusterId>,
+    pub helm_chart_id: Option<HelmChartId>,
     pub config: Option<serde_json::Value>,
     pub values_override: Option<Option<serde_json::Value>>,
     pub enabled: Option<bool>,
@@ -64,13 +150,13 @@ pub struct DeploymentFilters {
     #[kv(optional)]
     pub name: Option<String>,
     #[kv(optional)]
-    pub kind_id: Option<String>,
+    pub kind_id: Option<DeploymentKindId>,
     #[kv(optional)]
-    pub cluster_id: Option<Uuid>,
+    pub cluster_id: Option<K8sClusterId>,
     #[kv(optional)]
     pub enabled: Option<bool>,
     #[kv(optional)]
-    pub env_id: Option<Uuid>,
+    pub env_id: Option<EnvId>,
 }
 
 impl PlatzClient {
@@ -82,7 +168,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn deployment(&self, deployment_id: Uuid) -> Result<Deployment> {
+    pub async fn deployment(&self, deployment_id: DeploymentId) -> Result<Deployment> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -94,7 +180,7 @@ impl PlatzClient {
 
     pub async fn update_deployment(
         &self,
-        deployment_id: Uuid,
+        deployment_id: DeploymentId,
         update_deployment: UpdateDeployment,
     ) -> Result<Deployment> {
         Ok(self
@@ -113,3 +199,142 @@ impl PlatzClient {
             .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    const STATUS: &str = r#"{
+        "status": {"name": "Running", "color": "success"},
+        "primary_metric": null,
+        "metrics": null
+    }"#;
+
+    fn deployment(reported_status: serde_json::Value) -> Deployment {
+        serde_json::from_value(deployment_json(reported_status)).unwrap()
+    }
+
+    fn deployment_json(reported_status: serde_json::Value) -> serde_json::Value {
+        json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "name": "",
+            "kind_id": Uuid::new_v4(),
+            "cluster_id": Uuid::new_v4(),
+            "enabled": true,
+            "status": "Running",
+            "description_md": null,
+            "reason": null,
+            "revision_id": null,
+            "reported_status": reported_status,
+            "helm_chart_id": Uuid::new_v4(),
+            "config": {},
+            "values_override": null,
+        })
+    }
+
+    fn status() -> serde_json::Value {
+        serde_json::from_str(STATUS).unwrap()
+    }
+
+    #[test]
+    fn deserializes_statuses() {
+        let status =
+            |value: &str| -> DeploymentStatus { serde_json::from_value(json!(value)).unwrap() };
+        assert!(matches!(status("Running"), DeploymentStatus::Running));
+        assert!(matches!(
+            status("Uninstalled"),
+            DeploymentStatus::Uninstalled
+        ));
+        let other = status("Hibernating");
+        assert!(matches!(&other, DeploymentStatus::Other(name) if name == "Hibernating"));
+        assert_eq!(other.to_string(), "Hibernating");
+    }
+
+    #[test]
+    fn reads_revision_id() {
+        assert_eq!(deployment(json!(null)).revision_id, None);
+
+        let revision_id = Uuid::new_v4();
+        let mut json = deployment_json(json!(null));
+        json["revision_id"] = json!(revision_id);
+        let deployment: Deployment = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            deployment.revision_id,
+            Some(DeploymentRevisionId(revision_id))
+        );
+    }
+
+    #[test]
+    fn reported_status_polled() {
+        let timestamp = Utc::now() - chrono::Duration::minutes(10);
+        let reported_status = deployment(json!({
+            "timestamp": timestamp,
+            "get_successful": true,
+            "content": status(),
+        }))
+        .reported_status_typed()
+        .unwrap()
+        .unwrap();
+        assert_eq!(reported_status.timestamp, Some(timestamp));
+        assert_eq!(
+            reported_status.status.as_ref().unwrap().status.name,
+            "Running"
+        );
+        assert!(reported_status.is_stale(chrono::Duration::minutes(5)));
+
+        let reported_status = deployment(json!({
+            "timestamp": Utc::now(),
+            "get_successful": false,
+            "content": null,
+        }))
+        .reported_status_typed()
+        .unwrap()
+        .unwrap();
+        assert!(reported_status.status.is_none());
+        assert!(reported_status.is_stale(chrono::Duration::minutes(5)));
+    }
+
+    #[test]
+    fn reported_status_content_only() {
+        let reported_status = deployment(status())
+            .reported_status_typed()
+            .unwrap()
+            .unwrap();
+        assert_eq!(reported_status.timestamp, None);
+        assert!(reported_status.get_successful);
+        assert_eq!(
+            reported_status.status.as_ref().unwrap().status.name,
+            "Running"
+        );
+    }
+
+    #[test]
+    fn fresh_or_unknown_age_is_not_stale() {
+        let fresh = deployment(json!({
+            "timestamp": Utc::now(),
+            "get_successful": true,
+            "content": status(),
+        }));
+        let fresh = fresh.reported_status_typed().unwrap().unwrap();
+        assert!(!fresh.is_stale(chrono::Duration::minutes(5)));
+        let unknown_age = deployment(status())
+            .reported_status_typed()
+            .unwrap()
+            .unwrap();
+        assert!(!unknown_age.is_stale(chrono::Duration::zero()));
+    }
+
+    #[test]
+    fn missing_or_invalid_reported_status() {
+        assert!(deployment(json!(null))
+            .reported_status_typed()
+            .unwrap()
+            .is_none());
+        assert!(deployment(json!({"timestamp": "yesterday"}))
+            .reported_status_typed()
+            .is_err());
+    }
+}
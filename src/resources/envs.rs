@@ -1,13 +1,13 @@
 use crate::client::PlatzClient;
+use crate::EnvId;
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Env {
-    pub id: Uuid,
+    pub id: EnvId,
     pub created_at: DateTime<Utc>,
     pub name: String,
     pub node_selector: serde_json::Value,
@@ -40,14 +40,14 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn env(&self, env_id: Uuid) -> Result<Env> {
+    pub async fn env(&self, env_id: EnvId) -> Result<Env> {
         Ok(self
             .request(reqwest::Method::GET, format!("/api/v2/envs/{env_id}"))
             .send()
             .await?)
     }
 
-    pub async fn update_env(&self, env_id: Uuid, update_env: UpdateEnv) -> Result<Env> {
+    pub async fn update_env(&self, env_id: EnvId, update_env: UpdateEnv) -> Result<Env> {
         Ok(self
             .request(reqwest::Method::GET, format!("/api/v2/envs/{env_id}"))
             .send_with_body(update_env)
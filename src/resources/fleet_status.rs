@@ -0,0 +1,706 @@
+use crate::client::PlatzClient;
+use crate::{
+    Deployment, DeploymentFilters, DeploymentId, DeploymentStatus, DeploymentTask,
+    DeploymentTaskId, DeploymentTaskStatus, Env, EnvFilters, EnvId, K8sCluster, K8sClusterFilters,
+    K8sClusterId, K8sResource, K8sResourceFilters, K8sResourceId,
+};
+use anyhow::Result;
+use chrono::prelude::*;
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+/// How many clusters' K8s resources or deployments' latest tasks are
+/// fetched concurrently.
+const FETCH_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct FleetStatusOptions {
+    /// Running deployments whose reported status is older than this are
+    /// reported as stale.
+    pub stale_after: chrono::Duration,
+}
+
+impl Default for FleetStatusOptions {
+    fn default() -> Self {
+        Self {
+            stale_after: chrono::Duration::minutes(10),
+        }
+    }
+}
+
+/// A summary of deployments' health, grouped by env and cluster, see
+/// `PlatzClient::fleet_status`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetStatus {
+    pub generated_at: DateTime<Utc>,
+    pub groups: Vec<FleetStatusGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetStatusGroup {
+    pub env_id: Option<EnvId>,
+    pub env_name: Option<String>,
+    pub cluster_id: K8sClusterId,
+    pub cluster_name: String,
+    pub deployment_count: usize,
+    /// Number of deployments in each `DeploymentStatus`.
+    pub status_counts: BTreeMap<String, usize>,
+    /// Deployments in the `Error` status.
+    pub errored: Vec<FleetDeployment>,
+    /// Running deployments without a recent reported status.
+    pub stale_statuses: Vec<FleetDeployment>,
+    /// Deployments whose latest task failed.
+    pub failed_tasks: Vec<FleetFailedTask>,
+    /// K8s resources with a `danger` status color.
+    pub red_resources: Vec<FleetRedResource>,
+}
+
+impl FleetStatusGroup {
+    fn new(
+        env_id: Option<EnvId>,
+        env_name: Option<String>,
+        cluster_id: K8sClusterId,
+        cluster_name: String,
+    ) -> Self {
+        Self {
+            env_id,
+            env_name,
+            cluster_id,
+            cluster_name,
+            deployment_count: 0,
+            status_counts: BTreeMap::new(),
+            errored: Vec::new(),
+            stale_statuses: Vec::new(),
+            failed_tasks: Vec::new(),
+            red_resources: Vec::new(),
+        }
+    }
+
+    fn unknown_cluster(cluster_id: K8sClusterId) -> Self {
+        Self::new(
+            None,
+            None,
+            cluster_id,
+            format!("unknown cluster {cluster_id}"),
+        )
+    }
+
+    pub fn has_problems(&self) -> bool {
+        !self.errored.is_empty()
+            || !self.stale_statuses.is_empty()
+            || !self.failed_tasks.is_empty()
+            || !self.red_resources.is_empty()
+    }
+
+    fn title(&self) -> String {
+        match self.env_name.as_deref() {
+            Some(env_name) => format!("{env_name} / {}", self.cluster_name),
+            None => self.cluster_name.clone(),
+        }
+    }
+
+    fn problem_lines(&self) -> Vec<String> {
+        let errored = self.errored.iter().map(|deployment| {
+            format!(
+                "Deployment {} is in error: {}",
+                deployment.name,
+                deployment.reason.as_deref().unwrap_or("no reason given")
+            )
+        });
+        let stale = self.stale_statuses.iter().map(|deployment| {
+            format!(
+                "Deployment {} has a stale reported status{}",
+                deployment.name,
+                deployment
+                    .reason
+                    .as_deref()
+                    .map(|reason| format!(" ({reason})"))
+                    .unwrap_or_default()
+            )
+        });
+        let failed_tasks = self.failed_tasks.iter().map(|task| {
+            format!(
+                "{} task of deployment {} failed: {}",
+                task.operation,
+                task.deployment_name,
+                task.reason.as_deref().unwrap_or("no reason given")
+            )
+        });
+        let red_resources = self.red_resources.iter().map(|resource| {
+            format!(
+                "Resource {} ({}) of deployment {} is red",
+                resource.name, resource.api_version, resource.deployment_name
+            )
+        });
+        errored
+            .chain(stale)
+            .chain(failed_tasks)
+            .chain(red_resources)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetDeployment {
+    pub id: DeploymentId,
+    pub name: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetFailedTask {
+    pub task_id: DeploymentTaskId,
+    pub deployment_id: DeploymentId,
+    pub deployment_name: String,
+    pub operation: String,
+    pub reason: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FleetRedResource {
+    pub resource_id: K8sResourceId,
+    pub deployment_id: DeploymentId,
+    pub deployment_name: String,
+    pub api_version: String,
+    pub name: String,
+}
+
+const TABLE_HEADERS: [&str; 8] = [
+    "Env",
+    "Cluster",
+    "Deployments",
+    "Running",
+    "Error",
+    "Stale",
+    "Failed tasks",
+    "Red resources",
+];
+
+impl FleetStatus {
+    pub fn has_problems(&self) -> bool {
+        self.groups.iter().any(FleetStatusGroup::has_problems)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    fn table_rows(&self) -> Vec<[String; 8]> {
+        self.groups
+            .iter()
+            .map(|group| {
+                [
+                    group.env_name.clone().unwrap_or_else(|| "-".to_owned()),
+                    group.cluster_name.clone(),
+                    group.deployment_count.to_string(),
+                    group
+                        .status_counts
+                        .get(&DeploymentStatus::Running.to_string())
+                        .copied()
+                        .unwrap_or_default()
+                        .to_string(),
+                    group.errored.len().to_string(),
+                    group.stale_statuses.len().to_string(),
+                    group.failed_tasks.len().to_string(),
+                    group.red_resources.len().to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    /// A Markdown report with a summary table and a list of problems for
+    /// each cluster.
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Fleet status {}\n\n", self.generated_at.to_rfc3339());
+        let _ = writeln!(output, "| {} |", TABLE_HEADERS.join(" | "));
+        let _ = writeln!(output, "|{}", "---|".repeat(TABLE_HEADERS.len()));
+        for row in self.table_rows() {
+            let cells: Vec<String> = row.iter().map(|cell| markdown_cell(cell)).collect();
+            let _ = writeln!(output, "| {} |", cells.join(" | "));
+        }
+        for group in self.groups.iter().filter(|group| group.has_problems()) {
+            let _ = write!(output, "\n## {}\n\n", group.title());
+            for line in group.problem_lines() {
+                let _ = writeln!(output, "- {line}");
+            }
+        }
+        output
+    }
+
+    /// A plain text report for terminals, with an aligned summary table
+    /// followed by the problems in each cluster.
+    pub fn to_table(&self) -> String {
+        let rows = self.table_rows();
+        let widths: Vec<usize> = TABLE_HEADERS
+            .iter()
+            .enumerate()
+            .map(|(index, header)| {
+                rows.iter()
+                    .map(|row| row[index].chars().count())
+                    .chain([header.len()])
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect();
+        let format_row = |cells: Vec<&str>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_owned()
+        };
+
+        let mut output = String::new();
+        let _ = writeln!(output, "{}", format_row(TABLE_HEADERS.to_vec()));
+        for row in rows.iter() {
+            let _ = writeln!(
+                output,
+                "{}",
+                format_row(row.iter().map(String::as_str).collect())
+            );
+        }
+        for group in self.groups.iter().filter(|group| group.has_problems()) {
+            let _ = write!(output, "\n{}:\n", group.title());
+            for line in group.problem_lines() {
+                let _ = writeln!(output, "  - {line}");
+            }
+        }
+        output
+    }
+}
+
+impl PlatzClient {
+    /// Summarize the health of the deployments matching `filters`, grouped
+    /// by env and cluster. This fetches the latest task of every deployment,
+    /// so it makes one request per deployment.
+    pub async fn fleet_status(&self, filters: DeploymentFilters) -> Result<FleetStatus> {
+        self.fleet_status_with_options(filters, FleetStatusOptions::default())
+            .await
+    }
+
+    pub async fn fleet_status_with_options(
+        &self,
+        filters: DeploymentFilters,
+        options: FleetStatusOptions,
+    ) -> Result<FleetStatus> {
+        let (deployments, clusters, envs) = futures::try_join!(
+            self.deployments(filters),
+            self.k8s_clusters(K8sClusterFilters::default()),
+            self.envs(EnvFilters::default()),
+        )?;
+        let cluster_ids: HashSet<K8sClusterId> = deployments
+            .iter()
+            .map(|deployment| deployment.cluster_id)
+            .collect();
+        let resources: Vec<K8sResource> =
+            futures::stream::iter(cluster_ids.into_iter().map(|cluster_id| {
+                self.k8s_resources(K8sResourceFilters {
+                    cluster_id: Some(cluster_id),
+                    ..Default::default()
+                })
+            }))
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .try_concat()
+            .await?;
+        let latest_tasks: HashMap<DeploymentId, DeploymentTask> =
+            futures::stream::iter(deployments.iter().map(|deployment| {
+                let deployment_id = deployment.id;
+                async move { self.latest_deployment_task(deployment_id).await }
+            }))
+            .buffer_unordered(FETCH_CONCURRENCY)
+            .try_collect::<Vec<_>>()
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|task| (task.deployment_id, task))
+            .collect();
+
+        Ok(FleetStatus::aggregate(
+            &deployments,
+            clusters,
+            envs,
+            resources,
+            &latest_tasks,
+            &options,
+        ))
+    }
+}
+
+impl FleetStatus {
+    /// Group the fetched deployments, their latest tasks and K8s resources
+    /// by cluster. Deployments and resources on a cluster missing from
+    /// `clusters` are counted in a group of their own, named after the
+    /// cluster's id.
+    fn aggregate(
+        deployments: &[Deployment],
+        clusters: Vec<K8sCluster>,
+        envs: Vec<Env>,
+        resources: Vec<K8sResource>,
+        latest_tasks: &HashMap<DeploymentId, DeploymentTask>,
+        options: &FleetStatusOptions,
+    ) -> Self {
+        let env_names: HashMap<EnvId, String> =
+            envs.into_iter().map(|env| (env.id, env.name)).collect();
+        let mut groups: HashMap<K8sClusterId, FleetStatusGroup> = clusters
+            .into_iter()
+            .map(|cluster| {
+                let env_name = cluster
+                    .env_id
+                    .and_then(|env_id| env_names.get(&env_id).cloned());
+                let group =
+                    FleetStatusGroup::new(cluster.env_id, env_name, cluster.id, cluster.name);
+                (cluster.id, group)
+            })
+            .collect();
+        let deployment_names: HashMap<DeploymentId, &str> = deployments
+            .iter()
+            .map(|deployment| (deployment.id, deployment.name.as_str()))
+            .collect();
+
+        for deployment in deployments.iter() {
+            let group = groups
+                .entry(deployment.cluster_id)
+                .or_insert_with(|| FleetStatusGroup::unknown_cluster(deployment.cluster_id));
+            group.deployment_count += 1;
+            *group
+                .status_counts
+                .entry(deployment.status.to_string())
+                .or_default() += 1;
+            if matches!(deployment.status, DeploymentStatus::Error) {
+                group.errored.push(FleetDeployment::from_deployment(
+                    deployment,
+                    deployment.reason.clone(),
+                ));
+            }
+            if let Some(reason) = stale_reason(deployment, options.stale_after) {
+                group
+                    .stale_statuses
+                    .push(FleetDeployment::from_deployment(deployment, reason));
+            }
+            if let Some(task) = latest_tasks.get(&deployment.id)
+                && matches!(task.status, DeploymentTaskStatus::Failed)
+            {
+                group.failed_tasks.push(FleetFailedTask {
+                    task_id: task.id,
+                    deployment_id: deployment.id,
+                    deployment_name: deployment.name.clone(),
+                    operation: task.operation.get_type_name(),
+                    reason: task.reason.clone(),
+                    finished_at: task.finished_at,
+                });
+            }
+        }
+
+        let deployment_ids: HashSet<DeploymentId> = deployment_names.keys().copied().collect();
+        for resource in resources
+            .into_iter()
+            .filter(|resource| deployment_ids.contains(&resource.deployment_id))
+            .filter(|resource| resource.is_danger())
+        {
+            let group = groups
+                .entry(resource.cluster_id)
+                .or_insert_with(|| FleetStatusGroup::unknown_cluster(resource.cluster_id));
+            group.red_resources.push(FleetRedResource {
+                resource_id: resource.id,
+                deployment_id: resource.deployment_id,
+                deployment_name: deployment_names[&resource.deployment_id].to_owned(),
+                api_version: resource.api_version,
+                name: resource.name,
+            });
+        }
+
+        let mut groups: Vec<_> = groups
+            .into_values()
+            .filter(|group| group.deployment_count > 0)
+            .collect();
+        groups.sort_by(|a, b| {
+            (&a.env_name, &a.cluster_name, a.cluster_id).cmp(&(
+                &b.env_name,
+                &b.cluster_name,
+                b.cluster_id,
+            ))
+        });
+        Self {
+            generated_at: Utc::now(),
+            groups,
+        }
+    }
+}
+
+impl FleetDeployment {
+    fn from_deployment(deployment: &Deployment, reason: Option<String>) -> Self {
+        Self {
+            id: deployment.id,
+            name: deployment.name.clone(),
+            reason,
+        }
+    }
+}
+
+/// Why a running deployment's reported status is stale, if it is. Returns
+/// `None` for deployments that don't report a status at all.
+fn stale_reason(deployment: &Deployment, stale_after: chrono::Duration) -> Option<Option<String>> {
+    if !matches!(deployment.status, DeploymentStatus::Running) {
+        return None;
+    }
+    match deployment.reported_status_typed() {
+        Ok(Some(reported_status)) if reported_status.is_stale(stale_after) => {
+            Some(match reported_status.age() {
+                Some(age) if !reported_status.get_successful => Some(format!(
+                    "last poll failed {} minutes ago",
+                    age.num_minutes()
+                )),
+                Some(age) => Some(format!("{} minutes old", age.num_minutes())),
+                None => None,
+            })
+        }
+        Ok(_) => None,
+        Err(err) => Some(Some(format!("invalid reported status: {err}"))),
+    }
+}
+
+/// Escape a Markdown table cell, so pipes and line breaks in names don't
+/// break the table.
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    fn cluster(id: K8sClusterId, env_id: EnvId, name: &str) -> K8sCluster {
+        serde_json::from_value(json!({
+            "id": id,
+            "env_id": env_id,
+            "provider_id": name,
+            "created_at": "2024-01-01T00:00:00Z",
+            "last_seen_at": "2024-01-01T00:00:00Z",
+            "name": name,
+            "region_name": "eu-west-1",
+            "is_ok": true,
+            "not_ok_reason": null,
+            "ignore": false,
+            "ingress_domain": null,
+            "ingress_class": null,
+            "ingress_tls_secret_name": null,
+            "grafana_url": null,
+            "grafana_datasource_name": null,
+        }))
+        .unwrap()
+    }
+
+    fn env(id: EnvId, name: &str) -> Env {
+        serde_json::from_value(json!({
+            "id": id,
+            "created_at": "2024-01-01T00:00:00Z",
+            "name": name,
+            "node_selector": [],
+            "tolerations": [],
+            "auto_add_new_users": false,
+        }))
+        .unwrap()
+    }
+
+    fn deployment(
+        cluster_id: K8sClusterId,
+        name: &str,
+        status: &str,
+        reported_status: Value,
+    ) -> Deployment {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "name": name,
+            "kind_id": Uuid::new_v4(),
+            "cluster_id": cluster_id,
+            "enabled": true,
+            "status": status,
+            "description_md": null,
+            "reason": (status == "Error").then_some("chart failed"),
+            "revision_id": null,
+            "reported_status": reported_status,
+            "helm_chart_id": Uuid::new_v4(),
+            "config": {},
+            "values_override": null,
+        }))
+        .unwrap()
+    }
+
+    fn polled(minutes_ago: i64, get_successful: bool) -> Value {
+        json!({
+            "timestamp": Utc::now() - chrono::Duration::minutes(minutes_ago),
+            "get_successful": get_successful,
+            "content": {
+                "status": {"name": "Running", "color": "success"},
+                "primary_metric": null,
+                "metrics": null,
+            },
+        })
+    }
+
+    fn failed_task(deployment: &Deployment) -> DeploymentTask {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "created_at": "2024-01-01T00:00:00Z",
+            "execute_at": "2024-01-01T00:00:00Z",
+            "first_attempted_at": null,
+            "started_at": null,
+            "finished_at": "2024-01-01T00:01:00Z",
+            "cluster_id": deployment.cluster_id,
+            "deployment_id": deployment.id,
+            "acting_user_id": null,
+            "acting_deployment_id": null,
+            "operation": {"Reinstall": {"reason": "manual"}},
+            "status": "Failed",
+            "reason": "timed out",
+            "canceled_by_user_id": null,
+            "canceled_by_deployment_id": null,
+        }))
+        .unwrap()
+    }
+
+    fn red_resource(deployment: &Deployment) -> K8sResource {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "last_updated_at": "2024-01-01T00:00:00Z",
+            "cluster_id": deployment.cluster_id,
+            "deployment_id": deployment.id,
+            "kind_id": Uuid::new_v4(),
+            "api_version": "apps/v1",
+            "name": "api-worker",
+            "status_color": ["danger"],
+            "metadata": {},
+        }))
+        .unwrap()
+    }
+
+    fn fleet_status() -> FleetStatus {
+        let env_id = EnvId(Uuid::new_v4());
+        let (prod, idle, unknown) = (
+            K8sClusterId(Uuid::new_v4()),
+            K8sClusterId(Uuid::new_v4()),
+            K8sClusterId(Uuid::new_v4()),
+        );
+        let deployments = vec![
+            deployment(prod, "web", "Running", polled(1, true)),
+            deployment(prod, "api", "Error", Value::Null),
+            deployment(prod, "worker", "Running", polled(30, true)),
+            deployment(prod, "cron", "Running", polled(2, false)),
+            deployment(unknown, "orphan", "Running", Value::Null),
+        ];
+        let latest_tasks = HashMap::from([(deployments[1].id, failed_task(&deployments[1]))]);
+        let resources = vec![red_resource(&deployments[1]), red_resource(&deployments[4])];
+        FleetStatus::aggregate(
+            &deployments,
+            vec![
+                cluster(prod, env_id, "prod|eu"),
+                cluster(idle, env_id, "idle"),
+            ],
+            vec![env(env_id, "production")],
+            resources,
+            &latest_tasks,
+            &FleetStatusOptions::default(),
+        )
+    }
+
+    #[test]
+    fn aggregates_by_cluster() {
+        let status = fleet_status();
+        assert!(status.has_problems());
+        // The idle cluster has no deployments, and the unknown cluster
+        // has no env, so it sorts first.
+        let [unknown, prod] = status.groups.as_slice() else {
+            panic!("unexpected groups {:?}", status.groups);
+        };
+
+        assert_eq!(prod.env_name.as_deref(), Some("production"));
+        assert_eq!(prod.deployment_count, 4);
+        assert_eq!(prod.status_counts["Running"], 3);
+        assert_eq!(prod.status_counts["Error"], 1);
+        assert_eq!(prod.errored[0].name, "api");
+        assert_eq!(prod.errored[0].reason.as_deref(), Some("chart failed"));
+        let stale: Vec<_> = prod
+            .stale_statuses
+            .iter()
+            .map(|deployment| deployment.name.as_str())
+            .collect();
+        assert_eq!(stale, vec!["worker", "cron"]);
+        assert_eq!(prod.failed_tasks[0].operation, "Reinstall");
+        assert_eq!(prod.red_resources[0].deployment_name, "api");
+
+        assert!(unknown.cluster_name.starts_with("unknown cluster "));
+        assert_eq!(unknown.deployment_count, 1);
+        assert_eq!(unknown.red_resources[0].deployment_name, "orphan");
+    }
+
+    #[test]
+    fn explains_stale_statuses() {
+        let cluster_id = K8sClusterId(Uuid::new_v4());
+        let stale_after = chrono::Duration::minutes(10);
+        let reason = |deployment: Deployment| stale_reason(&deployment, stale_after);
+        assert_eq!(
+            reason(deployment(cluster_id, "a", "Running", polled(1, true))),
+            None
+        );
+        assert_eq!(
+            reason(deployment(cluster_id, "a", "Running", polled(30, true))),
+            Some(Some("30 minutes old".to_owned()))
+        );
+        assert_eq!(
+            reason(deployment(cluster_id, "a", "Running", polled(2, false))),
+            Some(Some("last poll failed 2 minutes ago".to_owned()))
+        );
+        assert_eq!(
+            reason(deployment(cluster_id, "a", "Error", polled(30, false))),
+            None
+        );
+        assert!(matches!(
+            reason(deployment(cluster_id, "a", "Running", json!({"timestamp": 1}))),
+            Some(Some(reason)) if reason.starts_with("invalid reported status")
+        ));
+    }
+
+    #[test]
+    fn renders_markdown() {
+        let markdown = fleet_status().to_markdown();
+        let lines: Vec<&str> = markdown.lines().collect();
+        assert_eq!(
+            lines[2],
+            "| Env | Cluster | Deployments | Running | Error | Stale | Failed tasks | Red resources |"
+        );
+        assert_eq!(lines[3], "|---|---|---|---|---|---|---|---|");
+        assert!(lines[4].starts_with("| - | unknown cluster "));
+        assert_eq!(
+            lines[5],
+            r"| production | prod\|eu | 4 | 3 | 1 | 2 | 1 | 1 |"
+        );
+        assert!(markdown.contains("\n## production / prod|eu\n\n"));
+        assert!(markdown.contains("- Deployment api is in error: chart failed\n"));
+        assert!(markdown.contains("- Reinstall task of deployment api failed: timed out\n"));
+        assert!(markdown.contains("- Resource api-worker (apps/v1) of deployment api is red\n"));
+    }
+
+    #[test]
+    fn renders_table() {
+        let table = fleet_status().to_table();
+        let lines: Vec<&str> = table.lines().collect();
+        let cluster_column = lines[0].find("Cluster").unwrap();
+        let deployments_column = lines[0].find("Deployments").unwrap();
+        assert!(lines[2].starts_with("production  prod|eu"));
+        assert_eq!(lines[2].find("prod|eu"), Some(cluster_column));
+        assert_eq!(lines[2].find('4'), Some(deployments_column));
+        assert!(table.contains("\nproduction / prod|eu:\n"));
+        assert!(
+            table.contains("  - Deployment worker has a stale reported status (30 minutes old)\n")
+        );
+    }
+}
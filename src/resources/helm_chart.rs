@@ -1,15 +1,19 @@
 use crate::client::PlatzClient;
+use crate::{
+    DeploymentId, DeploymentKindId, DeploymentTask, HelmChartId, HelmRegistryId, HelmTagFormatId,
+};
 use anyhow::{bail, Result};
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::Deserialize;
-use uuid::Uuid;
+use std::cmp::Ordering;
+use std::str::FromStr;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HelmChart {
-    pub id: Uuid,
+    pub id: HelmChartId,
     pub created_at: DateTime<Utc>,
-    pub helm_registry_id: Uuid,
+    pub helm_registry_id: HelmRegistryId,
     pub image_digest: String,
     pub image_tag: String,
     pub available: bool,
@@ -18,7 +22,7 @@ pub struct HelmChart {
     pub features: Option<platz_chart_ext::ChartExtFeatures>,
     pub resource_types: Option<serde_json::Value>,
     pub error: Option<String>,
-    pub tag_format_id: Option<Uuid>,
+    pub tag_format_id: Option<HelmTagFormatId>,
     pub parsed_version: Option<String>,
     pub parsed_revision: Option<String>,
     pub parsed_branch: Option<String>,
@@ -40,18 +44,152 @@ impl HelmChart {
             Vec::new()
         })
     }
+
+    /// Parses `parsed_version` as a semantic version, if possible.
+    pub fn version(&self) -> Option<ChartVersion> {
+        self.parsed_version.as_deref()?.parse().ok()
+    }
+
+    /// Orders charts by semantic version, falling back to `created_at` for
+    /// charts without a parsable version. Versioned charts are considered
+    /// newer than unversioned ones.
+    pub fn cmp_version(&self, other: &Self) -> Ordering {
+        (self.version(), self.created_at).cmp(&(other.version(), other.created_at))
+    }
+}
+
+/// A semantic version parsed from a helm chart's tag. A leading `v` and
+/// build metadata are ignored, and missing minor/patch numbers are zero.
+#[derive(Debug, Clone)]
+pub struct ChartVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub pre_release: Option<String>,
+}
+
+impl FromStr for ChartVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().trim_start_matches('v');
+        let s = s.split_once('+').map_or(s, |(version, _build)| version);
+        let (core, pre_release) = match s.split_once('-') {
+            Some((core, pre_release)) => (core, Some(pre_release.to_owned())),
+            None => (s, None),
+        };
+        let mut parts = core.split('.');
+        let major = parts.next().unwrap_or_default().parse()?;
+        let minor = parts
+            .next()
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or_default();
+        let patch = parts
+            .next()
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or_default();
+        if parts.next().is_some() {
+            bail!("Too many components in chart version {s:?}");
+        }
+        Ok(Self {
+            major,
+            minor,
+            patch,
+            pre_release,
+        })
+    }
+}
+
+impl Ord for ChartVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre_release, &other.pre_release) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => cmp_pre_release(a, b),
+            })
+    }
+}
+
+/// Compares pre-release tags as semver does: dot separated identifiers are
+/// compared in order, numerically if both are numbers, numbers sort before
+/// other identifiers, and a tag that's a prefix of another sorts first.
+fn cmp_pre_release(a: &str, b: &str) -> Ordering {
+    let mut a = a.split('.');
+    let mut b = b.split('.');
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a), Some(b)) => match (a.parse::<u64>(), b.parse::<u64>()) {
+                (Ok(a), Ok(b)) => a.cmp(&b),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => a.cmp(b),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl PartialEq for ChartVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ChartVersion {}
+
+impl PartialOrd for ChartVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Which newer chart versions `upgrade_to_latest` may upgrade to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradePolicy {
+    /// Same major and minor version.
+    PatchOnly,
+    /// Same major version.
+    Minor,
+    /// Any newer version, including charts without a parsable version.
+    Any,
+}
+
+impl UpgradePolicy {
+    pub fn allows(&self, current: &HelmChart, candidate: &HelmChart) -> bool {
+        if candidate.cmp_version(current) != Ordering::Greater {
+            return false;
+        }
+        match (self, current.version(), candidate.version()) {
+            (Self::Any, _, _) => true,
+            (Self::Minor, Some(current), Some(candidate)) => current.major == candidate.major,
+            (Self::PatchOnly, Some(current), Some(candidate)) => {
+                current.major == candidate.major && current.minor == candidate.minor
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Default, IntoVec)]
 pub struct HelmChartFilters {
     #[kv(optional)]
-    pub helm_registry_id: Option<Uuid>,
+    pub helm_registry_id: Option<HelmRegistryId>,
     #[kv(optional)]
     pub parsed_branch: Option<String>,
     #[kv(optional)]
     pub in_use: Option<bool>,
     #[kv(optional)]
-    pub kind_id: Option<Uuid>,
+    pub kind_id: Option<DeploymentKindId>,
 }
 
 impl PlatzClient {
@@ -62,7 +200,7 @@ impl PlatzClient {
             .paginated()
             .await?)
     }
-    pub async fn helm_chart(&self, helm_chart_id: Uuid) -> Result<HelmChart> {
+    pub async fn helm_chart(&self, helm_chart_id: HelmChartId) -> Result<HelmChart> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -71,4 +209,146 @@ impl PlatzClient {
             .send()
             .await?)
     }
+
+    /// Returns the newest available chart of a deployment kind, optionally
+    /// limited to a branch. See `HelmChart::cmp_version` for the ordering.
+    pub async fn latest_helm_chart(
+        &self,
+        kind_id: DeploymentKindId,
+        branch: Option<&str>,
+    ) -> Result<Option<HelmChart>> {
+        Ok(self
+            .helm_charts(HelmChartFilters {
+                kind_id: Some(kind_id),
+                parsed_branch: branch.map(ToOwned::to_owned),
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .filter(|chart| chart.available && chart.error.is_none())
+            .max_by(HelmChart::cmp_version))
+    }
+
+    /// Upgrades a deployment to the newest chart on a branch that is allowed
+    /// by `policy`. Returns `None` if the deployment is already up to date.
+    pub async fn upgrade_to_latest(
+        &self,
+        deployment_id: DeploymentId,
+        branch: Option<&str>,
+        policy: UpgradePolicy,
+    ) -> Result<Option<DeploymentTask>> {
+        let deployment = self.deployment(deployment_id).await?;
+        let current_chart = self.helm_chart(deployment.helm_chart_id).await?;
+        let latest_chart = self
+            .helm_charts(HelmChartFilters {
+                kind_id: Some(deployment.kind_id),
+                parsed_branch: branch.map(ToOwned::to_owned),
+                ..Default::default()
+            })
+            .await?
+            .into_iter()
+            .filter(|chart| chart.available && chart.error.is_none())
+            .filter(|chart| policy.allows(&current_chart, chart))
+            .max_by(HelmChart::cmp_version);
+
+        Ok(match latest_chart {
+            Some(chart) => Some(self.upgrade_deployment(deployment_id, chart.id).await?),
+            None => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn version(s: &str) -> ChartVersion {
+        s.parse().unwrap()
+    }
+
+    fn chart(parsed_version: Option<&str>, created_at: &str) -> HelmChart {
+        serde_json::from_value(serde_json::json!({
+            "id": Uuid::new_v4(),
+            "created_at": created_at,
+            "helm_registry_id": Uuid::new_v4(),
+            "image_digest": "sha256:0",
+            "image_tag": parsed_version.unwrap_or("latest"),
+            "available": true,
+            "values_ui": null,
+            "actions_schema": null,
+            "features": null,
+            "resource_types": null,
+            "error": null,
+            "tag_format_id": null,
+            "parsed_version": parsed_version,
+            "parsed_revision": null,
+            "parsed_branch": null,
+            "parsed_commit": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parses_versions() {
+        let parsed = version("v1.2.3-rc.1+build.5");
+        assert_eq!((parsed.major, parsed.minor, parsed.patch), (1, 2, 3));
+        assert_eq!(parsed.pre_release.as_deref(), Some("rc.1"));
+
+        let parsed = version("2");
+        assert_eq!((parsed.major, parsed.minor, parsed.patch), (2, 0, 0));
+        assert_eq!(parsed.pre_release, None);
+
+        assert!("1.2.3.4".parse::<ChartVersion>().is_err());
+        assert!("latest".parse::<ChartVersion>().is_err());
+        assert!("1.x".parse::<ChartVersion>().is_err());
+    }
+
+    #[test]
+    fn orders_versions() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "1.0.1",
+            "1.1.0",
+            "2.0.0",
+        ];
+        for pair in ordered.windows(2) {
+            assert!(version(pair[0]) < version(pair[1]), "{pair:?}");
+        }
+        assert!(version("1.0.0-rc.10") > version("1.0.0-rc.2"));
+        assert_eq!(version("v1.0.0+a"), version("1.0.0+b"));
+    }
+
+    #[test]
+    fn upgrade_policy() {
+        let current = chart(Some("1.2.3"), "2024-01-01T00:00:00Z");
+        let patch = chart(Some("1.2.4"), "2024-01-02T00:00:00Z");
+        let minor = chart(Some("1.3.0"), "2024-01-02T00:00:00Z");
+        let major = chart(Some("2.0.0"), "2024-01-02T00:00:00Z");
+        let older = chart(Some("1.2.2"), "2024-01-02T00:00:00Z");
+        let unversioned = chart(None, "2024-01-02T00:00:00Z");
+
+        assert!(UpgradePolicy::PatchOnly.allows(&current, &patch));
+        assert!(!UpgradePolicy::PatchOnly.allows(&current, &minor));
+        assert!(!UpgradePolicy::PatchOnly.allows(&current, &major));
+
+        assert!(UpgradePolicy::Minor.allows(&current, &patch));
+        assert!(UpgradePolicy::Minor.allows(&current, &minor));
+        assert!(!UpgradePolicy::Minor.allows(&current, &major));
+
+        assert!(UpgradePolicy::Any.allows(&current, &major));
+        assert!(!UpgradePolicy::Any.allows(&current, &older));
+        assert!(!UpgradePolicy::Any.allows(&current, &current));
+        // Versioned charts are newer than unversioned ones.
+        assert!(!UpgradePolicy::Any.allows(&current, &unversioned));
+        assert!(UpgradePolicy::Any.allows(&unversioned, &current));
+        assert!(!UpgradePolicy::Minor.allows(&unversioned, &current));
+    }
 }
@@ -1,17 +1,17 @@
 use crate::client::PlatzClient;
+use crate::{DeploymentKindId, HelmRegistryId};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HelmRegistry {
-    pub id: Uuid,
+    pub id: HelmRegistryId,
     pub created_at: DateTime<Utc>,
     pub domain_name: String,
     pub repo_name: String,
-    pub kind_id: Uuid,
+    pub kind_id: DeploymentKindId,
     pub available: bool,
     pub fa_icon: String,
 }
@@ -21,7 +21,7 @@ pub struct HelmRegistryFilters {
     #[kv(optional)]
     pub repo_name: Option<String>,
     #[kv(optional)]
-    pub kind_id: Option<Uuid>,
+    pub kind_id: Option<DeploymentKindId>,
 }
 
 #[derive(Debug, Serialize)]
@@ -38,7 +38,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn helm_registry(&self, registry_id: Uuid) -> Result<HelmRegistry> {
+    pub async fn helm_registry(&self, registry_id: HelmRegistryId) -> Result<HelmRegistry> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -50,7 +50,7 @@ impl PlatzClient {
 
     pub async fn update_helm_registry(
         &self,
-        registry_id: Uuid,
+        registry_id: HelmRegistryId,
         update_registry: UpdateHelmRegistry,
     ) -> Result<HelmRegistry> {
         Ok(self
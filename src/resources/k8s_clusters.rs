@@ -1,14 +1,16 @@
 use crate::client::PlatzClient;
+use crate::{EnvId, K8sClusterId};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use std::ops::Range;
+use url::Url;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct K8sCluster {
-    pub id: Uuid,
-    pub env_id: Option<Uuid>,
+    pub id: K8sClusterId,
+    pub env_id: Option<EnvId>,
     pub provider_id: String,
     pub created_at: DateTime<Utc>,
     pub last_seen_at: DateTime<Utc>,
@@ -24,10 +26,95 @@ pub struct K8sCluster {
     pub grafana_datasource_name: Option<String>,
 }
 
-#[derive(Default, IntoVec, Debug, Serialize)]
+impl K8sCluster {
+    /// The host a deployment's ingress gets on this cluster, under the
+    /// cluster's ingress domain.
+    pub fn ingress_host(&self, deployment_host_name: &str) -> Option<String> {
+        let domain = self.ingress_domain.as_deref()?.trim_matches('.');
+        Some(format!("{deployment_host_name}.{domain}"))
+    }
+
+    /// The public URL of a deployment on this cluster. It uses HTTPS if the
+    /// cluster has an ingress TLS secret.
+    pub fn ingress_url(&self, deployment_host_name: &str) -> Option<Url> {
+        let scheme = if self.ingress_tls_secret_name.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        let host = self.ingress_host(deployment_host_name)?;
+        Url::parse(&format!("{scheme}://{host}/")).ok()
+    }
+
+    /// A Grafana Explore link showing logs of a namespace in the cluster's
+    /// Grafana datasource during `window`. Without an `org_id` Grafana
+    /// opens it in the user's current organization.
+    pub fn grafana_explore_url(
+        &self,
+        namespace: &str,
+        window: Range<DateTime<Utc>>,
+        org_id: Option<u64>,
+    ) -> Option<Url> {
+        let mut url = self.grafana_base_url()?.join("explore").ok()?;
+        let pane = serde_json::json!({
+            "datasource": self.grafana_datasource_name,
+            "queries": [{
+                "refId": "A",
+                "expr": format!("{{namespace=\"{namespace}\"}}"),
+            }],
+            "range": {
+                "from": window.start.timestamp_millis().to_string(),
+                "to": window.end.timestamp_millis().to_string(),
+            },
+        });
+        if let Some(org_id) = org_id {
+            url.query_pairs_mut()
+                .append_pair("orgId", &org_id.to_string());
+        }
+        url.query_pairs_mut().append_pair("left", &pane.to_string());
+        Some(url)
+    }
+
+    /// A link to a Grafana dashboard with its `namespace` variable set and
+    /// its time range set to `window`, in organization `org_id` if given.
+    pub fn grafana_dashboard_url(
+        &self,
+        dashboard_uid: &str,
+        namespace: &str,
+        window: Range<DateTime<Utc>>,
+        org_id: Option<u64>,
+    ) -> Option<Url> {
+        let mut url = self
+            .grafana_base_url()?
+            .join(&format!("d/{dashboard_uid}"))
+            .ok()?;
+        if let Some(org_id) = org_id {
+            url.query_pairs_mut()
+                .append_pair("orgId", &org_id.to_string());
+        }
+        url.query_pairs_mut()
+            .append_pair("var-namespace", namespace)
+            .append_pair("from", &window.start.timestamp_millis().to_string())
+            .append_pair("to", &window.end.timestamp_millis().to_string());
+        if let Some(datasource) = self.grafana_datasource_name.as_deref() {
+            url.query_pairs_mut()
+                .append_pair("var-datasource", datasource);
+        }
+        Some(url)
+    }
+
+    /// `grafana_url` with a trailing slash, so relative paths are joined
+    /// under it.
+    fn grafana_base_url(&self) -> Option<Url> {
+        let grafana_url = self.grafana_url.as_deref()?;
+        Url::parse(&format!("{}/", grafana_url.trim_end_matches('/'))).ok()
+    }
+}
+
+#[derive(Default, IntoVec, Debug, Clone, Serialize)]
 pub struct K8sClusterFilters {
     #[kv(optional)]
-    pub env_id: Option<Uuid>,
+    pub env_id: Option<EnvId>,
     #[kv(optional)]
     pub name: Option<String>,
 }
@@ -35,7 +122,7 @@ pub struct K8sClusterFilters {
 #[derive(Debug, Serialize)]
 pub struct UpdateK8sCluster {
     #[serde(default, with = "::serde_with::rust::double_option")]
-    pub env_id: Option<Option<Uuid>>,
+    pub env_id: Option<Option<EnvId>>,
     pub ignore: Option<bool>,
     #[serde(default, with = "::serde_with::rust::double_option")]
     pub ingress_domain: Option<Option<String>>,
@@ -58,7 +145,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn k8s_cluster(&self, k8s_cluster_id: Uuid) -> Result<K8sCluster> {
+    pub async fn k8s_cluster(&self, k8s_cluster_id: K8sClusterId) -> Result<K8sCluster> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -70,7 +157,7 @@ impl PlatzClient {
 
     pub async fn update_k8s_cluster(
         &self,
-        k8s_cluster_id: Uuid,
+        k8s_cluster_id: K8sClusterId,
         update_deployment: UpdateK8sCluster,
     ) -> Result<K8sCluster> {
         Ok(self
@@ -82,3 +169,126 @@ impl PlatzClient {
             .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn cluster(settings: serde_json::Value) -> K8sCluster {
+        let mut cluster = json!({
+            "id": Uuid::new_v4(),
+            "env_id": null,
+            "provider_id": "cluster",
+            "created_at": "2024-01-01T00:00:00Z",
+            "last_seen_at": "2024-01-01T00:00:00Z",
+            "name": "cluster",
+            "region_name": "eu-west-1",
+            "is_ok": true,
+            "not_ok_reason": null,
+            "ignore": false,
+            "ingress_domain": null,
+            "ingress_class": null,
+            "ingress_tls_secret_name": null,
+            "grafana_url": null,
+            "grafana_datasource_name": null,
+        });
+        cluster
+            .as_object_mut()
+            .unwrap()
+            .extend(settings.as_object().unwrap().clone());
+        serde_json::from_value(cluster).unwrap()
+    }
+
+    fn window() -> Range<DateTime<Utc>> {
+        Utc.timestamp_millis_opt(1_700_000_000_000).unwrap()
+            ..Utc.timestamp_millis_opt(1_700_000_600_000).unwrap()
+    }
+
+    fn query_pairs(url: &Url) -> HashMap<String, String> {
+        url.query_pairs().into_owned().collect()
+    }
+
+    #[test]
+    fn builds_ingress_urls() {
+        let plain_cluster = cluster(json!({"ingress_domain": ".apps.example.com."}));
+        assert_eq!(
+            plain_cluster.ingress_host("web").as_deref(),
+            Some("web.apps.example.com")
+        );
+        assert_eq!(
+            plain_cluster.ingress_url("web").unwrap().as_str(),
+            "http://web.apps.example.com/"
+        );
+
+        let tls_cluster = cluster(json!({
+            "ingress_domain": "example.com",
+            "ingress_tls_secret_name": "tls",
+        }));
+        assert_eq!(
+            tls_cluster.ingress_url("web").unwrap().as_str(),
+            "https://web.example.com/"
+        );
+    }
+
+    #[test]
+    fn encodes_explore_query() {
+        let cluster = cluster(json!({
+            "grafana_url": "https://grafana.example.com/sub/",
+            "grafana_datasource_name": "Loki & co",
+        }));
+        let url = cluster
+            .grafana_explore_url("my-ns", window(), Some(3))
+            .unwrap();
+        assert_eq!(url.path(), "/sub/explore");
+        // The pane's braces, quotes and spaces must not leak into the URL.
+        let raw_query = url.query().unwrap();
+        assert!(!raw_query.contains(['{', '"', ' ']), "{raw_query}");
+
+        let query = query_pairs(&url);
+        assert_eq!(query["orgId"], "3");
+        let pane: serde_json::Value = serde_json::from_str(&query["left"]).unwrap();
+        assert_eq!(
+            pane,
+            json!({
+                "datasource": "Loki & co",
+                "queries": [{"refId": "A", "expr": "{namespace=\"my-ns\"}"}],
+                "range": {"from": "1700000000000", "to": "1700000600000"},
+            })
+        );
+
+        let url = cluster
+            .grafana_explore_url("my-ns", window(), None)
+            .unwrap();
+        assert!(!query_pairs(&url).contains_key("orgId"));
+    }
+
+    #[test]
+    fn builds_dashboard_urls() {
+        let cluster = cluster(json!({"grafana_url": "https://grafana.example.com"}));
+        let url = cluster
+            .grafana_dashboard_url("abc", "my ns", window(), Some(2))
+            .unwrap();
+        assert_eq!(url.path(), "/d/abc");
+        let query = query_pairs(&url);
+        assert_eq!(query["orgId"], "2");
+        assert_eq!(query["var-namespace"], "my ns");
+        assert_eq!(query["from"], "1700000000000");
+        assert_eq!(query["to"], "1700000600000");
+        assert!(!query.contains_key("var-datasource"));
+    }
+
+    #[test]
+    fn needs_ingress_and_grafana_settings() {
+        let cluster = cluster(json!({}));
+        assert_eq!(cluster.ingress_host("web"), None);
+        assert_eq!(cluster.ingress_url("web"), None);
+        assert_eq!(cluster.grafana_explore_url("ns", window(), None), None);
+        assert_eq!(
+            cluster.grafana_dashboard_url("abc", "ns", window(), None),
+            None
+        );
+    }
+}
@@ -1,33 +1,319 @@
-use crate::PlatzClient;
+use crate::{
+    DeploymentId, K8sClusterId, K8sResourceId, K8sResourceKindId, PlatzClient, StatusColor,
+};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::Deserialize;
-use uuid::Uuid;
+use serde_json::Value;
+use std::collections::HashMap;
+use strum::{Display, EnumString};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct K8sResource {
-    pub id: Uuid,
+    pub id: K8sResourceId,
     pub last_updated_at: DateTime<Utc>,
-    pub cluster_id: Uuid,
-    pub deployment_id: Uuid,
-    pub kind_id: Uuid,
+    pub cluster_id: K8sClusterId,
+    pub deployment_id: DeploymentId,
+    pub kind_id: K8sResourceKindId,
     pub api_version: String,
     pub name: String,
     pub status_color: Vec<String>,
     pub metadata: serde_json::Value,
 }
 
+/// A kind of K8s resource tracked by Platz, referenced by
+/// `K8sResource::kind_id`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct K8sResourceKind {
+    pub id: K8sResourceKindId,
+    pub name: String,
+}
+
+/// The kinds of K8s resources with typed accessors on `TypedK8sResource`.
+#[derive(Debug, Clone, PartialEq, Eq, Display, EnumString)]
+pub enum K8sKind {
+    Deployment,
+    StatefulSet,
+    Job,
+    CronJob,
+    Ingress,
+    Service,
+    #[strum(default)]
+    Other(String),
+}
+
+impl K8sKind {
+    /// The kind of a resource named `kind` in `api_version`, such as
+    /// `apps/v1`. Kinds of the same name in other API groups, such as custom
+    /// resources, are `Other`.
+    pub fn resolve(kind: &str, api_version: &str) -> Self {
+        let group = api_version.rsplit_once('/').map_or("", |(group, _)| group);
+        match (group, kind) {
+            ("apps", "Deployment") => Self::Deployment,
+            ("apps", "StatefulSet") => Self::StatefulSet,
+            ("batch", "Job") => Self::Job,
+            ("batch", "CronJob") => Self::CronJob,
+            ("networking.k8s.io", "Ingress") => Self::Ingress,
+            ("", "Service") => Self::Service,
+            _ => Self::Other(kind.to_owned()),
+        }
+    }
+}
+
+/// Replica counts of a Deployment or StatefulSet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicaCounts {
+    pub desired: u64,
+    pub ready: u64,
+    pub updated: u64,
+    pub available: u64,
+}
+
+impl ReplicaCounts {
+    pub fn is_ready(&self) -> bool {
+        self.ready >= self.desired
+    }
+}
+
+/// Completion state of a Job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobCompletion {
+    pub completions: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub active: u64,
+}
+
+impl JobCompletion {
+    pub fn is_complete(&self) -> bool {
+        self.succeeded >= self.completions
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum K8sResourceError {
+    #[error("K8s resource {name} has unknown kind {kind_id}")]
+    UnknownKind {
+        name: String,
+        kind_id: K8sResourceKindId,
+    },
+    #[error("K8s resource {name} only has its ObjectMeta, not its spec and status")]
+    ObjectMetaOnly { name: String },
+    #[error("K8s resource {name} has no {field} in its metadata")]
+    MissingField { name: String, field: &'static str },
+}
+
+/// A K8s resource with its kind resolved from `kind_id` and `api_version`,
+/// see `PlatzClient::typed_k8s_resources`.
+#[derive(Debug, Clone)]
+pub struct TypedK8sResource {
+    pub resource: K8sResource,
+    pub kind: K8sKind,
+}
+
+impl K8sResource {
+    /// Resolve the resource's kind from the kinds returned by
+    /// `PlatzClient::k8s_resource_kinds`.
+    pub fn typed(
+        self,
+        kinds: &HashMap<K8sResourceKindId, K8sResourceKind>,
+    ) -> Result<TypedK8sResource, K8sResourceError> {
+        let kind = kinds
+            .get(&self.kind_id)
+            .ok_or_else(|| K8sResourceError::UnknownKind {
+                name: self.name.clone(),
+                kind_id: self.kind_id,
+            })?;
+        Ok(TypedK8sResource {
+            kind: K8sKind::resolve(&kind.name, &self.api_version),
+            resource: self,
+        })
+    }
+
+    /// The resource's ObjectMeta, with its `namespace`, `labels` and
+    /// `annotations`. `metadata` holds either the ObjectMeta or the whole
+    /// K8s object.
+    pub fn object_meta(&self) -> &Value {
+        self.metadata
+            .get("metadata")
+            .filter(|object_meta| object_meta.is_object())
+            .unwrap_or(&self.metadata)
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.object_meta().get("namespace")?.as_str()
+    }
+
+    /// The status colors that are known `StatusColor` values, ignoring any
+    /// others.
+    pub fn status_colors(&self) -> Vec<StatusColor> {
+        self.status_color
+            .iter()
+            .filter_map(|color| color.parse().ok())
+            .collect()
+    }
+
+    /// The most severe of the resource's status colors.
+    pub fn worst_status_color(&self) -> Option<StatusColor> {
+        self.status_colors()
+            .into_iter()
+            .max_by_key(StatusColor::severity)
+    }
+
+    pub fn is_danger(&self) -> bool {
+        self.status_colors().contains(&StatusColor::Danger)
+    }
+}
+
+/// The kind-specific accessors read the `spec` and `status` of the whole K8s
+/// object. When `metadata` only holds the resource's ObjectMeta they return
+/// `K8sResourceError::ObjectMetaOnly`, and `K8sResource::status_colors` is
+/// the way to tell whether the resource is healthy. Accessors for a specific
+/// kind return `None` or nothing for resources of other kinds.
+impl TypedK8sResource {
+    /// Replica counts of a Deployment or StatefulSet. Missing status counts
+    /// are zero, as the K8s API omits them when there are no such replicas.
+    pub fn replicas(&self) -> Result<Option<ReplicaCounts>, K8sResourceError> {
+        if !matches!(self.kind, K8sKind::Deployment | K8sKind::StatefulSet) {
+            return Ok(None);
+        }
+        let spec = self.section("spec")?;
+        let status = self.section("status")?;
+        Ok(Some(ReplicaCounts {
+            desired: u64_field(spec, "replicas").unwrap_or(1),
+            ready: u64_field(status, "readyReplicas").unwrap_or(0),
+            updated: u64_field(status, "updatedReplicas").unwrap_or(0),
+            available: u64_field(status, "availableReplicas").unwrap_or(0),
+        }))
+    }
+
+    /// Whether all replicas of a Deployment or StatefulSet are ready, or
+    /// `None` for other kinds.
+    pub fn is_ready(&self) -> Result<Option<bool>, K8sResourceError> {
+        Ok(self.replicas()?.map(|replicas| replicas.is_ready()))
+    }
+
+    /// Completion state of a Job.
+    pub fn job_completion(&self) -> Result<Option<JobCompletion>, K8sResourceError> {
+        if self.kind != K8sKind::Job {
+            return Ok(None);
+        }
+        let spec = self.section("spec")?;
+        let status = self.section("status")?;
+        Ok(Some(JobCompletion {
+            completions: u64_field(spec, "completions").unwrap_or(1),
+            succeeded: u64_field(status, "succeeded").unwrap_or(0),
+            failed: u64_field(status, "failed").unwrap_or(0),
+            active: u64_field(status, "active").unwrap_or(0),
+        }))
+    }
+
+    /// Schedule of a CronJob.
+    pub fn cron_schedule(&self) -> Result<Option<&str>, K8sResourceError> {
+        if self.kind != K8sKind::CronJob {
+            return Ok(None);
+        }
+        Ok(self
+            .section("spec")?
+            .get("schedule")
+            .and_then(Value::as_str))
+    }
+
+    /// Last time a CronJob was scheduled, `None` if it never ran.
+    pub fn last_schedule_time(&self) -> Result<Option<DateTime<Utc>>, K8sResourceError> {
+        if self.kind != K8sKind::CronJob {
+            return Ok(None);
+        }
+        Ok(self
+            .section("status")?
+            .get("lastScheduleTime")
+            .and_then(Value::as_str)
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| time.with_timezone(&Utc)))
+    }
+
+    /// Hosts of an Ingress's rules.
+    pub fn ingress_hosts(&self) -> Result<Vec<String>, K8sResourceError> {
+        if self.kind != K8sKind::Ingress {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .section("spec")?
+            .get("rules")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|rule| rule.get("host")?.as_str())
+            .map(ToOwned::to_owned)
+            .collect())
+    }
+
+    /// Type of a Service, such as `ClusterIP` or `LoadBalancer`.
+    pub fn service_type(&self) -> Result<Option<&str>, K8sResourceError> {
+        if self.kind != K8sKind::Service {
+            return Ok(None);
+        }
+        Ok(Some(
+            self.section("spec")?
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("ClusterIP"),
+        ))
+    }
+
+    /// Ports of a Service.
+    pub fn service_ports(&self) -> Result<Vec<u16>, K8sResourceError> {
+        if self.kind != K8sKind::Service {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .section("spec")?
+            .get("ports")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|port| port.get("port")?.as_u64()?.try_into().ok())
+            .collect())
+    }
+
+    fn section(&self, field: &'static str) -> Result<&Value, K8sResourceError> {
+        let metadata = &self.resource.metadata;
+        if metadata.get("spec").is_none() && metadata.get("status").is_none() {
+            return Err(K8sResourceError::ObjectMetaOnly {
+                name: self.resource.name.clone(),
+            });
+        }
+        metadata
+            .get(field)
+            .filter(|section| section.is_object())
+            .ok_or_else(|| K8sResourceError::MissingField {
+                name: self.resource.name.clone(),
+                field,
+            })
+    }
+}
+
+fn u64_field(section: &Value, field: &str) -> Option<u64> {
+    section.get(field)?.as_u64()
+}
+
+#[derive(Default, IntoVec)]
+pub struct K8sResourceKindFilters {
+    #[kv(optional)]
+    pub name: Option<String>,
+}
+
 #[derive(Default, IntoVec)]
 pub struct K8sResourceFilters {
     #[kv(optional)]
     pub name: Option<String>,
     #[kv(optional)]
-    pub kind_id: Option<Uuid>,
+    pub kind_id: Option<K8sResourceKindId>,
     #[kv(optional)]
-    pub cluster_id: Option<Uuid>,
+    pub cluster_id: Option<K8sClusterId>,
     #[kv(optional)]
-    pub deployment_id: Option<Uuid>,
+    pub deployment_id: Option<DeploymentId>,
 }
 
 impl PlatzClient {
@@ -39,7 +325,24 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn k8s_resource(&self, deployment_resource_id: Uuid) -> Result<K8sResource> {
+    /// K8s resources with their kinds resolved, see `K8sResource::typed`.
+    pub async fn typed_k8s_resources(
+        &self,
+        filters: K8sResourceFilters,
+    ) -> Result<Vec<TypedK8sResource>> {
+        let (resources, kinds) = futures::try_join!(
+            self.k8s_resources(filters),
+            self.k8s_resource_kinds(K8sResourceKindFilters::default()),
+        )?;
+        let kinds: HashMap<K8sResourceKindId, K8sResourceKind> =
+            kinds.into_iter().map(|kind| (kind.id, kind)).collect();
+        Ok(resources
+            .into_iter()
+            .map(|resource| resource.typed(&kinds))
+            .collect::<Result<_, _>>()?)
+    }
+
+    pub async fn k8s_resource(&self, deployment_resource_id: K8sResourceId) -> Result<K8sResource> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -48,4 +351,174 @@ impl PlatzClient {
             .send()
             .await?)
     }
+
+    pub async fn k8s_resource_kinds(
+        &self,
+        filters: K8sResourceKindFilters,
+    ) -> Result<Vec<K8sResourceKind>> {
+        Ok(self
+            .request(reqwest::Method::GET, "/api/v2/k8s-resource-kinds")
+            .add_to_query(filters.into_vec())
+            .paginated()
+            .await?)
+    }
+
+    pub async fn k8s_resource_kind(
+        &self,
+        k8s_resource_kind_id: K8sResourceKindId,
+    ) -> Result<K8sResourceKind> {
+        Ok(self
+            .request(
+                reqwest::Method::GET,
+                format!("/api/v2/k8s-resource-kinds/{k8s_resource_kind_id}"),
+            )
+            .send()
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn resource(api_version: &str, metadata: Value, status_color: &[&str]) -> K8sResource {
+        serde_json::from_value(json!({
+            "id": Uuid::new_v4(),
+            "last_updated_at": "2024-01-01T00:00:00Z",
+            "cluster_id": Uuid::new_v4(),
+            "deployment_id": Uuid::new_v4(),
+            "kind_id": Uuid::nil(),
+            "api_version": api_version,
+            "name": "web",
+            "status_color": status_color,
+            "metadata": metadata,
+        }))
+        .unwrap()
+    }
+
+    fn typed(kind: &str, api_version: &str, metadata: Value) -> TypedK8sResource {
+        let kinds = HashMap::from([(
+            K8sResourceKindId(Uuid::nil()),
+            K8sResourceKind {
+                id: K8sResourceKindId(Uuid::nil()),
+                name: kind.to_owned(),
+            },
+        )]);
+        resource(api_version, metadata, &[]).typed(&kinds).unwrap()
+    }
+
+    #[test]
+    fn resolves_kinds() {
+        assert_eq!(
+            K8sKind::resolve("Deployment", "apps/v1"),
+            K8sKind::Deployment
+        );
+        assert_eq!(K8sKind::resolve("CronJob", "batch/v1"), K8sKind::CronJob);
+        assert_eq!(
+            K8sKind::resolve("Ingress", "networking.k8s.io/v1"),
+            K8sKind::Ingress
+        );
+        assert_eq!(K8sKind::resolve("Service", "v1"), K8sKind::Service);
+        assert_eq!(
+            K8sKind::resolve("Deployment", "example.com/v1"),
+            K8sKind::Other("Deployment".to_owned())
+        );
+
+        let unknown = resource("apps/v1", json!({}), &[]).typed(&HashMap::new());
+        assert!(matches!(unknown, Err(K8sResourceError::UnknownKind { .. })));
+    }
+
+    /// `metadata` holding only the resource's ObjectMeta.
+    #[test]
+    fn reads_object_meta() {
+        let object_meta = json!({
+            "name": "web",
+            "namespace": "app-web",
+            "uid": "5f0c2b5e-3f0a-4c36-9a83-0a4f3c1e2d10",
+            "resourceVersion": "123456",
+            "generation": 3,
+            "creationTimestamp": "2024-01-01T00:00:00Z",
+            "labels": {"app.kubernetes.io/managed-by": "Helm"},
+            "annotations": {"meta.helm.sh/release-name": "web"},
+        });
+        let deployment = typed("Deployment", "apps/v1", object_meta.clone());
+        assert_eq!(deployment.kind, K8sKind::Deployment);
+        assert_eq!(deployment.resource.namespace(), Some("app-web"));
+        assert!(matches!(
+            deployment.replicas(),
+            Err(K8sResourceError::ObjectMetaOnly { .. })
+        ));
+
+        let ingress = typed("Ingress", "networking.k8s.io/v1", object_meta.clone());
+        assert!(matches!(
+            ingress.ingress_hosts(),
+            Err(K8sResourceError::ObjectMetaOnly { .. })
+        ));
+        // Kinds without typed accessors don't need more than the ObjectMeta.
+        let config_map = typed("ConfigMap", "v1", object_meta);
+        assert_eq!(config_map.replicas().unwrap(), None);
+        assert!(config_map.ingress_hosts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_full_objects() {
+        let deployment = typed(
+            "Deployment",
+            "apps/v1",
+            json!({
+                "metadata": {"name": "web", "namespace": "app-web"},
+                "spec": {"replicas": 3},
+                "status": {"readyReplicas": 2, "updatedReplicas": 3},
+            }),
+        );
+        assert_eq!(deployment.resource.namespace(), Some("app-web"));
+        let replicas = deployment.replicas().unwrap().unwrap();
+        assert_eq!(
+            (replicas.desired, replicas.ready, replicas.available),
+            (3, 2, 0)
+        );
+        assert_eq!(deployment.is_ready().unwrap(), Some(false));
+        assert_eq!(deployment.job_completion().unwrap(), None);
+        assert!(deployment.ingress_hosts().unwrap().is_empty());
+
+        let ingress = typed(
+            "Ingress",
+            "networking.k8s.io/v1",
+            json!({"spec": {"rules": [{"host": "a.example.com"}, {"http": {}}]}}),
+        );
+        assert_eq!(ingress.ingress_hosts().unwrap(), vec!["a.example.com"]);
+
+        let job = typed(
+            "Job",
+            "batch/v1",
+            json!({"spec": {"completions": 2}, "status": {"succeeded": 2}}),
+        );
+        assert!(job.job_completion().unwrap().unwrap().is_complete());
+
+        let without_status = typed("StatefulSet", "apps/v1", json!({"spec": {}}));
+        assert!(matches!(
+            without_status.replicas(),
+            Err(K8sResourceError::MissingField {
+                field: "status",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parses_status_colors() {
+        let resource = resource("v1", json!({}), &["success", "purple", "warning", "danger"]);
+        assert_eq!(
+            resource.status_colors(),
+            vec![
+                StatusColor::Success,
+                StatusColor::Warning,
+                StatusColor::Danger
+            ]
+        );
+        assert_eq!(resource.worst_status_color(), Some(StatusColor::Danger));
+        assert!(resource.is_danger());
+    }
 }
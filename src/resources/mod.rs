@@ -1,9 +1,13 @@
+mod clone_deployment;
+mod cluster_health;
 mod deployment_kinds;
+mod deployment_links;
 mod deployment_resource_types;
 mod deployment_resources;
 mod deployment_tasks;
 mod deployments;
 mod envs;
+mod fleet_status;
 mod helm_chart;
 mod helm_registries;
 mod k8s_clusters;
@@ -12,12 +16,16 @@ mod secrets;
 mod user_tokens;
 mod users;
 
+pub use clone_deployment::*;
+pub use cluster_health::*;
 pub use deployment_kinds::*;
+pub use deployment_links::*;
 pub use deployment_resource_types::*;
 pub use deployment_resources::*;
 pub use deployment_tasks::*;
 pub use deployments::*;
 pub use envs::*;
+pub use fleet_status::*;
 pub use helm_chart::*;
 pub use helm_registries::*;
 pub use k8s_clusters::*;
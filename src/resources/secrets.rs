@@ -1,16 +1,16 @@
 use crate::client::PlatzClient;
+use crate::{EnvId, SecretId, SecretString};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Secret {
-    pub id: Uuid,
+    pub id: SecretId,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub env_id: Uuid,
+    pub env_id: EnvId,
     pub collection: String,
     pub name: String,
 }
@@ -20,7 +20,7 @@ pub struct SecretFilters {
     #[kv(optional)]
     pub name: Option<String>,
     #[kv(optional)]
-    pub env_id: Option<Uuid>,
+    pub env_id: Option<EnvId>,
     #[kv(optional)]
     pub collection: Option<String>,
 }
@@ -28,15 +28,15 @@ pub struct SecretFilters {
 #[derive(Debug, Serialize)]
 pub struct UpdateSecret {
     name: Option<String>,
-    contents: Option<String>,
+    contents: Option<SecretString>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct NewSecret {
-    pub env_id: Uuid,
+    pub env_id: EnvId,
     pub collection: String,
     pub name: String,
-    pub contents: String,
+    pub contents: SecretString,
 }
 
 impl PlatzClient {
@@ -48,7 +48,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn secret(&self, secret_id: Uuid) -> Result<Secret> {
+    pub async fn secret(&self, secret_id: SecretId) -> Result<Secret> {
         Ok(self
             .request(reqwest::Method::GET, format!("/api/v2/secrets/{secret_id}"))
             .send()
@@ -57,7 +57,7 @@ impl PlatzClient {
 
     pub async fn update_secret(
         &self,
-        secret_id: Uuid,
+        secret_id: SecretId,
         update_secret: UpdateSecret,
     ) -> Result<Secret> {
         Ok(self
@@ -1,31 +1,31 @@
 use crate::client::PlatzClient;
+use crate::{SecretString, UserId, UserTokenId};
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct UserToken {
-    pub id: Uuid,
-    pub user_id: Uuid,
+    pub id: UserTokenId,
+    pub user_id: UserId,
     pub created_at: DateTime<Utc>,
 }
 
 #[derive(Default, IntoVec)]
 pub struct UserTokenFilters {
     #[kv(optional)]
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<UserId>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct NewUserToken {
-    pub user_id: Option<Uuid>,
+    pub user_id: Option<UserId>,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UserTokenCreationResponse {
-    pub created_token: String,
+    pub created_token: SecretString,
 }
 
 impl PlatzClient {
@@ -37,7 +37,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn user_token(&self, token_id: Uuid) -> Result<UserToken> {
+    pub async fn user_token(&self, token_id: UserTokenId) -> Result<UserToken> {
         Ok(self
             .request(
                 reqwest::Method::GET,
@@ -57,7 +57,7 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn delete_user_token(&self, token_id: Uuid) -> Result<()> {
+    pub async fn delete_user_token(&self, token_id: UserTokenId) -> Result<()> {
         Ok(self
             .request(
                 reqwest::Method::DELETE,
@@ -1,13 +1,13 @@
 use crate::client::PlatzClient;
+use crate::UserId;
 use anyhow::Result;
 use chrono::prelude::*;
 use kv_derive::{prelude::*, IntoVec};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct User {
-    pub id: Uuid,
+    pub id: UserId,
     pub created_at: DateTime<Utc>,
     pub display_name: String,
     pub email: String,
@@ -40,14 +40,14 @@ impl PlatzClient {
             .await?)
     }
 
-    pub async fn user(&self, user_id: Uuid) -> Result<User> {
+    pub async fn user(&self, user_id: UserId) -> Result<User> {
         Ok(self
             .request(reqwest::Method::GET, format!("/api/v2/users/{user_id}"))
             .send()
             .await?)
     }
 
-    pub async fn update_user(&self, user_id: Uuid, update_user: UpdateUser) -> Result<User> {
+    pub async fn update_user(&self, user_id: UserId, update_user: UpdateUser) -> Result<User> {
         Ok(self
             .request(reqwest::Method::PUT, format!("/api/v2/users/{user_id}"))
             .send_with_body(update_user)
@@ -0,0 +1,5 @@
+mod reporter;
+#[cfg(feature = "status-server")]
+mod server;
+
+pub use reporter::*;
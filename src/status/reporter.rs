@@ -0,0 +1,83 @@
+use crate::PlatzStatus;
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+
+/// A shared handle to a service's current `PlatzStatus`. Clones share the
+/// same status, so application code can update it from anywhere while
+/// it's being served, see `StatusReporter::serve` (requires the
+/// `status-server` feature) or `StatusReporter::to_json` for serving it
+/// from an existing HTTP server.
+#[derive(Debug)]
+pub struct StatusReporter<SN>
+where
+    SN: Clone + Serialize,
+{
+    status: Arc<RwLock<PlatzStatus<SN>>>,
+}
+
+impl<SN> Clone for StatusReporter<SN>
+where
+    SN: Clone + Serialize,
+{
+    fn clone(&self) -> Self {
+        Self {
+            status: self.status.clone(),
+        }
+    }
+}
+
+impl<SN> StatusReporter<SN>
+where
+    SN: Clone + Serialize,
+{
+    pub fn new(status: PlatzStatus<SN>) -> Self {
+        Self {
+            status: Arc::new(RwLock::new(status)),
+        }
+    }
+
+    pub fn get(&self) -> PlatzStatus<SN> {
+        self.status
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set(&self, status: PlatzStatus<SN>) {
+        *self
+            .status
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = status;
+    }
+
+    /// Change the current status in place, e.g. to update a single metric.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut PlatzStatus<SN>),
+    {
+        f(&mut self
+            .status
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()));
+    }
+
+    /// The current status as the JSON body Platz expects from the status
+    /// endpoint.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(
+            &*self
+                .status
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+}
+
+impl<SN> From<PlatzStatus<SN>> for StatusReporter<SN>
+where
+    SN: Clone + Serialize,
+{
+    fn from(status: PlatzStatus<SN>) -> Self {
+        Self::new(status)
+    }
+}
@@ -0,0 +1,148 @@
+use super::reporter::StatusReporter;
+use async_std::future::timeout;
+use async_std::io::prelude::*;
+use async_std::io::BufReader;
+use async_std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use async_std::task::spawn;
+use futures::StreamExt;
+use serde::Serialize;
+use std::time::Duration;
+use tracing::debug;
+
+/// The longest request head accepted, since only the request line is
+/// needed.
+const MAX_REQUEST_HEAD: u64 = 8 * 1024;
+
+/// How long a client may take to send the request head, so that idle
+/// connections don't hold a task forever.
+const REQUEST_HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+impl<SN> StatusReporter<SN>
+where
+    SN: Clone + Serialize + Send + Sync + 'static,
+{
+    /// Serve the current status as JSON on `GET path`, which should match
+    /// the `status.path` in the chart's `platz/features.yaml`. Runs until
+    /// the listener fails or the returned future is dropped.
+    pub async fn serve<A>(&self, addr: A, path: &str) -> std::io::Result<()>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr).await?;
+        let mut incoming = listener.incoming();
+        while let Some(stream) = incoming.next().await {
+            let reporter = self.clone();
+            let path = path.to_owned();
+            spawn(async move {
+                if let Err(err) = reporter.handle(stream?, &path).await {
+                    debug!("Error serving status request: {err}");
+                }
+                std::io::Result::Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, stream: TcpStream, path: &str) -> std::io::Result<()> {
+        let request_line = timeout(REQUEST_HEAD_TIMEOUT, read_request_head(&stream))
+            .await
+            .map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "timed out reading the request head",
+                )
+            })??;
+
+        let mut parts = request_line.split_whitespace();
+        let (method, target) = (parts.next(), parts.next());
+        let (status, body) = match (method, target) {
+            (Some("GET" | "HEAD"), Some(target)) if target.split('?').next() == Some(path) => {
+                match self.to_json() {
+                    Ok(json) => ("200 OK", json),
+                    Err(err) => ("500 Internal Server Error", err.to_string()),
+                }
+            }
+            (Some("GET" | "HEAD"), Some(_)) => ("404 Not Found", String::new()),
+            (Some(_), Some(_)) => ("405 Method Not Allowed", String::new()),
+            _ => ("400 Bad Request", String::new()),
+        };
+        let content_type = if status.starts_with("200") {
+            "application/json"
+        } else {
+            "text/plain"
+        };
+        let allow = if status.starts_with("405") {
+            "Allow: GET, HEAD\r\n"
+        } else {
+            ""
+        };
+        // HEAD gets the same headers as GET, including the Content-Length,
+        // without the body.
+        let content_length = body.len();
+        let body = if method == Some("HEAD") { "" } else { &body };
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {content_length}\r\n{allow}Connection: close\r\n\r\n{body}",
+        );
+        (&stream).write_all(response.as_bytes()).await?;
+        (&stream).flush().await
+    }
+}
+
+/// Read the request head and return its request line.
+async fn read_request_head(stream: &TcpStream) -> std::io::Result<String> {
+    let mut reader = BufReader::new(stream.take(MAX_REQUEST_HEAD));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // Drain the rest of the request head
+    let mut line = String::new();
+    while reader.read_line(&mut line).await? > 2 {
+        line.clear();
+    }
+    Ok(request_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PlatzStatus, StatusColor};
+    use async_std::task::block_on;
+
+    fn request(raw_request: &str) -> String {
+        block_on(async {
+            let reporter = StatusReporter::new(
+                PlatzStatus::builder("Running".to_owned(), StatusColor::Success)
+                    .build()
+                    .unwrap(),
+            );
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let mut client = TcpStream::connect(listener.local_addr().unwrap())
+                .await
+                .unwrap();
+            let (server, _) = listener.accept().await.unwrap();
+            client.write_all(raw_request.as_bytes()).await.unwrap();
+            reporter.handle(server, "/status").await.unwrap();
+            let mut response = String::new();
+            client.read_to_string(&mut response).await.unwrap();
+            response
+        })
+    }
+
+    #[test]
+    fn head_is_answered_like_get_without_body() {
+        let get = request("GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        let head = request("HEAD /status HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        let (get_head, get_body) = get.split_once("\r\n\r\n").unwrap();
+        assert!(get_head.starts_with("HTTP/1.1 200 OK"));
+        assert!(get_body.contains("Running"));
+        assert_eq!(head, format!("{get_head}\r\n\r\n"));
+    }
+
+    #[test]
+    fn other_methods_are_not_allowed() {
+        let response = request("POST /status HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed"));
+        assert!(response.contains("Allow: GET, HEAD\r\n"));
+        let response = request("HEAD /other HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
@@ -1,8 +1,8 @@
 use rust_decimal::Decimal;
-use serde::Serialize;
-use strum::Display;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PlatzStatus<SN>
 where
     SN: Clone + Serialize,
@@ -10,10 +10,11 @@ where
     pub status: Status<SN>,
     pub primary_metric: Option<Metric>,
     pub metrics: Option<Vec<Metric>>,
+    #[serde(default)]
     pub notices: Vec<Notice>,
 }
 
-#[derive(Debug, Clone, Serialize, Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
 #[serde(rename_all = "lowercase")]
 pub enum StatusColor {
     #[strum(serialize = "primary")]
@@ -28,7 +29,19 @@ pub enum StatusColor {
     Secondary,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl StatusColor {
+    /// How bad a color is, for picking the worst of several colors. Danger
+    /// is the worst, followed by warning.
+    pub fn severity(&self) -> u8 {
+        match self {
+            Self::Danger => 2,
+            Self::Warning => 1,
+            Self::Primary | Self::Success | Self::Secondary => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Status<SN>
 where
     SN: Clone + Serialize,
@@ -37,7 +50,7 @@ where
     pub color: StatusColor,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Metric {
     pub value: Decimal,
     pub unit: String,
@@ -45,15 +58,328 @@ pub struct Metric {
     pub color: Option<StatusColor>,
 }
 
-#[derive(Debug, Clone, Serialize, Display)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Display)]
 pub enum NoticeLevel {
     Info,
     Warning,
     Danger,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Notice {
     pub level: NoticeLevel,
     pub text: String,
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum PlatzStatusError {
+    #[error("Primary metric {0:?} is missing from metrics")]
+    PrimaryMetricNotInMetrics(String),
+
+    #[error("Metric {0:?} has an empty unit")]
+    EmptyMetricUnit(String),
+
+    #[error("Metric with value {0} has an empty description")]
+    EmptyMetricDescription(Decimal),
+
+    #[error("Notice text is empty")]
+    EmptyNoticeText,
+}
+
+impl Metric {
+    pub fn new<U, D>(value: Decimal, unit: U, short_description: D) -> Self
+    where
+        U: Into<String>,
+        D: Into<String>,
+    {
+        Self {
+            value,
+            unit: unit.into(),
+            short_description: short_description.into(),
+            color: None,
+        }
+    }
+
+    pub fn with_color(mut self, color: StatusColor) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    fn validate(&self) -> Result<(), PlatzStatusError> {
+        if self.short_description.trim().is_empty() {
+            return Err(PlatzStatusError::EmptyMetricDescription(self.value));
+        }
+        if self.unit.trim().is_empty() {
+            return Err(PlatzStatusError::EmptyMetricUnit(
+                self.short_description.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Notice {
+    pub fn new<S>(level: NoticeLevel, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            level,
+            text: text.into(),
+        }
+    }
+}
+
+impl<SN> PlatzStatus<SN>
+where
+    SN: Clone + Serialize,
+{
+    pub fn builder(name: SN, color: StatusColor) -> PlatzStatusBuilder<SN> {
+        PlatzStatusBuilder::new(name, color)
+    }
+
+    /// Checks that every metric has a description and a unit, that the
+    /// primary metric also appears in `metrics`, and that notices aren't
+    /// empty.
+    pub fn validate(&self) -> Result<(), PlatzStatusError> {
+        let metrics = self.metrics.as_deref().unwrap_or_default();
+        for metric in metrics.iter().chain(self.primary_metric.as_ref()) {
+            metric.validate()?;
+        }
+        if let Some(primary_metric) = self.primary_metric.as_ref()
+            && !metrics.contains(primary_metric)
+        {
+            return Err(PlatzStatusError::PrimaryMetricNotInMetrics(
+                primary_metric.short_description.clone(),
+            ));
+        }
+        if self
+            .notices
+            .iter()
+            .any(|notice| notice.text.trim().is_empty())
+        {
+            return Err(PlatzStatusError::EmptyNoticeText);
+        }
+        Ok(())
+    }
+}
+
+impl PlatzStatus<String> {
+    /// A `Running` status with a success color.
+    pub fn running() -> Self {
+        Self::builder("Running".to_owned(), StatusColor::Success).into_status()
+    }
+
+    /// A `Degraded` status with a warning color, and a warning notice
+    /// explaining why.
+    pub fn degraded<S>(reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::builder("Degraded".to_owned(), StatusColor::Warning)
+            .warning(reason)
+            .into_status()
+    }
+
+    /// A `Failed` status with a danger color, and a danger notice
+    /// explaining why.
+    pub fn failed<S>(reason: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::builder("Failed".to_owned(), StatusColor::Danger)
+            .danger(reason)
+            .into_status()
+    }
+}
+
+/// Builds a `PlatzStatus`, see `PlatzStatus::builder`.
+#[derive(Debug, Clone)]
+pub struct PlatzStatusBuilder<SN>
+where
+    SN: Clone + Serialize,
+{
+    status: Status<SN>,
+    primary_metric: Option<Metric>,
+    metrics: Vec<Metric>,
+    notices: Vec<Notice>,
+}
+
+impl<SN> PlatzStatusBuilder<SN>
+where
+    SN: Clone + Serialize,
+{
+    pub fn new(name: SN, color: StatusColor) -> Self {
+        Self {
+            status: Status { name, color },
+            primary_metric: None,
+            metrics: Vec::new(),
+            notices: Vec::new(),
+        }
+    }
+
+    pub fn status(mut self, name: SN, color: StatusColor) -> Self {
+        self.status = Status { name, color };
+        self
+    }
+
+    pub fn color(mut self, color: StatusColor) -> Self {
+        self.status.color = color;
+        self
+    }
+
+    pub fn metric(mut self, metric: Metric) -> Self {
+        self.metrics.push(metric);
+        self
+    }
+
+    /// Set the primary metric, also adding it to the metrics.
+    pub fn primary_metric(mut self, metric: Metric) -> Self {
+        if !self.metrics.contains(&metric) {
+            self.metrics.push(metric.clone());
+        }
+        self.primary_metric = Some(metric);
+        self
+    }
+
+    pub fn notice<S>(mut self, level: NoticeLevel, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notices.push(Notice::new(level, text));
+        self
+    }
+
+    pub fn info<S>(self, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notice(NoticeLevel::Info, text)
+    }
+
+    pub fn warning<S>(self, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notice(NoticeLevel::Warning, text)
+    }
+
+    pub fn danger<S>(self, text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.notice(NoticeLevel::Danger, text)
+    }
+
+    fn into_status(self) -> PlatzStatus<SN> {
+        PlatzStatus {
+            status: self.status,
+            primary_metric: self.primary_metric,
+            metrics: (!self.metrics.is_empty()).then_some(self.metrics),
+            notices: self.notices,
+        }
+    }
+
+    /// Build the status, failing if it doesn't pass `PlatzStatus::validate`.
+    pub fn build(self) -> Result<PlatzStatus<SN>, PlatzStatusError> {
+        let status = self.into_status();
+        status.validate()?;
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metric(description: &str, unit: &str) -> Metric {
+        Metric::new(Decimal::from(42), unit, description)
+    }
+
+    fn builder() -> PlatzStatusBuilder<String> {
+        PlatzStatus::builder("Running".to_owned(), StatusColor::Success)
+    }
+
+    #[test]
+    fn builds_valid_status() {
+        let status = builder()
+            .primary_metric(metric("Requests", "req/s"))
+            .metric(metric("Errors", "%").with_color(StatusColor::Warning))
+            .info("Deployed from main")
+            .build()
+            .unwrap();
+        // The primary metric is added to the metrics only once.
+        assert_eq!(status.metrics.as_ref().unwrap().len(), 2);
+
+        let json = serde_json::to_value(&status).unwrap();
+        let parsed: PlatzStatus<String> = serde_json::from_value(json).unwrap();
+        parsed.validate().unwrap();
+        assert_eq!(parsed.status.name, "Running");
+        assert_eq!(parsed.status.color, StatusColor::Success);
+        assert_eq!(parsed.primary_metric, status.primary_metric);
+        assert_eq!(parsed.metrics, status.metrics);
+        assert_eq!(parsed.notices, status.notices);
+    }
+
+    #[test]
+    fn rejects_invalid_metrics_and_notices() {
+        assert!(matches!(
+            builder().metric(metric(" ", "%")).build(),
+            Err(PlatzStatusError::EmptyMetricDescription(_))
+        ));
+        assert!(matches!(
+            builder().metric(metric("Errors", "")).build(),
+            Err(PlatzStatusError::EmptyMetricUnit(description)) if description == "Errors"
+        ));
+        assert!(matches!(
+            builder().primary_metric(metric("Errors", "")).build(),
+            Err(PlatzStatusError::EmptyMetricUnit(_))
+        ));
+        assert!(matches!(
+            builder().warning("  ").build(),
+            Err(PlatzStatusError::EmptyNoticeText)
+        ));
+    }
+
+    #[test]
+    fn primary_metric_must_be_in_metrics() {
+        let mut status = builder()
+            .primary_metric(metric("Requests", "req/s"))
+            .build()
+            .unwrap();
+        status.metrics = Some(vec![metric("Errors", "%")]);
+        assert!(matches!(
+            status.validate(),
+            Err(PlatzStatusError::PrimaryMetricNotInMetrics(description))
+                if description == "Requests"
+        ));
+        status.metrics = None;
+        assert!(matches!(
+            status.validate(),
+            Err(PlatzStatusError::PrimaryMetricNotInMetrics(_))
+        ));
+    }
+
+    #[test]
+    fn constructors() {
+        let running = PlatzStatus::running();
+        assert_eq!(
+            (running.status.name.as_str(), &running.status.color),
+            ("Running", &StatusColor::Success)
+        );
+        assert!(running.notices.is_empty());
+        running.validate().unwrap();
+
+        let degraded = PlatzStatus::degraded("Replica down");
+        assert_eq!(degraded.status.color, StatusColor::Warning);
+        assert_eq!(
+            degraded.notices,
+            vec![Notice::new(NoticeLevel::Warning, "Replica down")]
+        );
+
+        let failed = PlatzStatus::failed("Database unreachable");
+        assert_eq!(failed.status.name, "Failed");
+        assert_eq!(failed.status.color, StatusColor::Danger);
+        assert_eq!(failed.notices[0].level, NoticeLevel::Danger);
+    }
+}
@@ -0,0 +1,336 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub type JsonDiff = HashMap<String, (Value, Value)>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JsonDiffError {
+    #[error("Invalid path in JSON diff: {0:?}")]
+    InvalidPath(String),
+
+    #[error("Value at {0:?} does not match the diff's old value")]
+    Conflict(String),
+}
+
+/// How paths are written in the keys of a `JsonDiff`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonDiffKeys {
+    /// Dot separated keys, e.g. `image.tag` or `hosts.0`. This is the format
+    /// Platz uses for `config_delta`. Object keys containing dots can't be
+    /// told apart from nested keys in this format, see `json_diff` and
+    /// `apply_json_diff` for how they are handled.
+    #[default]
+    Dotted,
+    /// RFC 6901 JSON pointers, e.g. `/image/tag` or `/hosts/0`.
+    Pointer,
+}
+
+impl JsonDiffKeys {
+    fn child(&self, path: &str, key: &str) -> String {
+        match self {
+            Self::Dotted if path.is_empty() => key.to_owned(),
+            Self::Dotted => format!("{path}.{key}"),
+            Self::Pointer => format!("{path}/{}", key.replace('~', "~0").replace('/', "~1")),
+        }
+    }
+
+    fn split(path: &str) -> PathKeys {
+        if path.is_empty() {
+            PathKeys {
+                keys: Vec::new(),
+                dotted: false,
+            }
+        } else if let Some(pointer) = path.strip_prefix('/') {
+            PathKeys {
+                keys: pointer
+                    .split('/')
+                    .map(|key| key.replace("~1", "/").replace("~0", "~"))
+                    .collect(),
+                dotted: false,
+            }
+        } else {
+            PathKeys {
+                keys: path.split('.').map(ToOwned::to_owned).collect(),
+                dotted: true,
+            }
+        }
+    }
+}
+
+/// The keys of a path in a diff. In dotted paths, an object key may span
+/// several of the keys when it contains dots.
+struct PathKeys {
+    keys: Vec<String>,
+    dotted: bool,
+}
+
+impl PathKeys {
+    /// The key of `value` that the path continues with from `keys[pos..]`,
+    /// and how many of the keys it spans. For dotted paths, the longest run
+    /// of keys that's an existing key of the object is used.
+    fn next(&self, value: &Value, pos: usize) -> (String, usize) {
+        let rest = &self.keys[pos..];
+        if self.dotted
+            && let Value::Object(map) = value
+        {
+            for len in (2..=rest.len()).rev() {
+                let key = rest[..len].join(".");
+                if map.contains_key(&key) {
+                    return (key, len);
+                }
+            }
+        }
+        (rest[0].clone(), 1)
+    }
+}
+
+/// Computes the difference between two JSON values. Objects are compared
+/// recursively, and every changed leaf is keyed by its dotted path with
+/// the old and new values. Missing keys are represented as `null`, so an
+/// object where a key changes to or from an explicit `null`, or where a
+/// changed key contains a dot, is recorded as a whole instead.
+pub fn json_diff(old: &Value, new: &Value) -> JsonDiff {
+    json_diff_with_keys(old, new, JsonDiffKeys::Dotted)
+}
+
+/// Same as `json_diff`, with a choice of key format. Arrays of the same
+/// length are compared element by element, otherwise they are replaced
+/// as a whole. With `JsonDiffKeys::Pointer`, keys containing dots don't
+/// cause their object to be recorded as a whole.
+pub fn json_diff_with_keys(old: &Value, new: &Value, keys: JsonDiffKeys) -> JsonDiff {
+    let mut diff = JsonDiff::new();
+    diff_into(&mut diff, keys, String::new(), old, new);
+    diff
+}
+
+fn diff_into(diff: &mut JsonDiff, keys: JsonDiffKeys, path: String, old: &Value, new: &Value) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            let added_keys: Vec<&String> = new_map
+                .keys()
+                .filter(|key| !old_map.contains_key(*key))
+                .collect();
+            let ambiguous = old_map.keys().chain(added_keys.iter().copied()).any(|key| {
+                let (old_value, new_value) = (old_map.get(key), new_map.get(key));
+                old_value != new_value
+                    && (new_value == Some(&Value::Null)
+                        || (old_value == Some(&Value::Null) && new_value.is_none())
+                        || (keys == JsonDiffKeys::Dotted && key.contains('.')))
+            });
+            if ambiguous {
+                diff.insert(path, (old.clone(), new.clone()));
+                return;
+            }
+            for key in old_map.keys().chain(added_keys) {
+                diff_into(
+                    diff,
+                    keys,
+                    keys.child(&path, key),
+                    old_map.get(key).unwrap_or(&Value::Null),
+                    new_map.get(key).unwrap_or(&Value::Null),
+                );
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items))
+            if old_items.len() == new_items.len() =>
+        {
+            for (index, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                diff_into(
+                    diff,
+                    keys,
+                    keys.child(&path, &index.to_string()),
+                    old_item,
+                    new_item,
+                );
+            }
+        }
+        _ if old != new => {
+            diff.insert(path, (old.clone(), new.clone()));
+        }
+        _ => {}
+    }
+}
+
+/// Applies a diff produced by `json_diff` to `target`. Both dotted and JSON
+/// pointer keys are accepted. Each path must currently hold the diff's old
+/// value, otherwise `JsonDiffError::Conflict` is returned and `target` may
+/// be partially updated. A `null` new value removes the key. In dotted
+/// paths, keys containing dots are matched against existing keys, longest
+/// first, so such keys can be changed or removed but not added.
+pub fn apply_json_diff(target: &mut Value, diff: &JsonDiff) -> Result<(), JsonDiffError> {
+    for (path, (old, new)) in sorted_entries(diff) {
+        let current = lookup(target, path).cloned().unwrap_or(Value::Null);
+        if current == *new {
+            continue;
+        }
+        if current != *old {
+            return Err(JsonDiffError::Conflict(path.clone()));
+        }
+        set(target, path, new.clone())?;
+    }
+    Ok(())
+}
+
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let keys = JsonDiffKeys::split(path);
+    let mut value = value;
+    let mut pos = 0;
+    while pos < keys.keys.len() {
+        let (key, len) = keys.next(value, pos);
+        value = match value {
+            Value::Object(map) => map.get(&key)?,
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+        pos += len;
+    }
+    Some(value)
+}
+
+fn set(target: &mut Value, path: &str, new: Value) -> Result<(), JsonDiffError> {
+    let keys = JsonDiffKeys::split(path);
+    let invalid_path = || JsonDiffError::InvalidPath(path.to_owned());
+    let mut current = target;
+    let mut pos = 0;
+    loop {
+        if pos == keys.keys.len() {
+            *current = new;
+            return Ok(());
+        }
+        if current.is_null() {
+            *current = Value::Object(Default::default());
+        }
+        let (key, len) = keys.next(current, pos);
+        pos += len;
+        let last = pos == keys.keys.len();
+        current = match current {
+            Value::Object(map) if last && new.is_null() => {
+                map.remove(&key);
+                return Ok(());
+            }
+            Value::Object(map) => map.entry(key).or_insert(Value::Null),
+            Value::Array(items) => key
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(invalid_path)?,
+            _ => return Err(invalid_path()),
+        };
+    }
+}
+
+fn sorted_entries(diff: &JsonDiff) -> Vec<(&String, &(Value, Value))> {
+    let mut entries: Vec<_> = diff.iter().collect();
+    entries.sort_by_key(|(path, _)| *path);
+    entries
+}
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a diff in a unified-diff like format, one `-`/`+` line pair per
+/// changed path, sorted by path. Added paths only get a `+` line and
+/// removed paths only get a `-` line. When `colored` is set, lines are
+/// wrapped in ANSI color codes.
+pub fn render_json_diff(diff: &JsonDiff, colored: bool) -> String {
+    let (red, green, reset) = if colored {
+        (RED, GREEN, RESET)
+    } else {
+        ("", "", "")
+    };
+    let mut output = String::new();
+    for (path, (old, new)) in sorted_entries(diff) {
+        if !old.is_null() {
+            output.push_str(&format!("{red}- {path}: {old}{reset}\n"));
+        }
+        if !new.is_null() {
+            output.push_str(&format!("{green}+ {path}: {new}{reset}\n"));
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn assert_round_trip(old: Value, new: Value) {
+        for keys in [JsonDiffKeys::Dotted, JsonDiffKeys::Pointer] {
+            let diff = json_diff_with_keys(&old, &new, keys);
+            let mut target = old.clone();
+            apply_json_diff(&mut target, &diff).unwrap();
+            assert_eq!(target, new, "{keys:?} diff {diff:?}");
+        }
+    }
+
+    #[test]
+    fn round_trips_nested_changes() {
+        assert_round_trip(
+            json!({"image": {"tag": "1.0", "repo": "app"}, "replicas": 1}),
+            json!({"image": {"tag": "1.1", "repo": "app"}, "env": {"DEBUG": "1"}}),
+        );
+        assert_round_trip(json!(1), json!({"a": 1}));
+    }
+
+    #[test]
+    fn round_trips_keys_with_dots() {
+        assert_round_trip(json!({"k.with.dot": 1}), json!({"k.with.dot": 2}));
+        let annotations = |value: Value| json!({"ingress": {"annotations": value}});
+        assert_round_trip(
+            annotations(json!({"nginx.ingress.kubernetes.io/rewrite-target": "/"})),
+            annotations(json!({"nginx.ingress.kubernetes.io/rewrite-target": "/api"})),
+        );
+        assert_round_trip(
+            annotations(json!({})),
+            annotations(json!({"nginx.ingress.kubernetes.io/ssl-redirect": "true"})),
+        );
+        assert_round_trip(
+            annotations(json!({"nginx.ingress.kubernetes.io/ssl-redirect": "true"})),
+            annotations(json!({})),
+        );
+    }
+
+    #[test]
+    fn round_trips_arrays() {
+        assert_round_trip(json!({"hosts": ["a", "b"]}), json!({"hosts": ["a", "c"]}));
+        assert_round_trip(json!({"hosts": ["a"]}), json!({"hosts": ["a", "b"]}));
+        assert_round_trip(json!({"hosts": ["a", "b"]}), json!({"hosts": []}));
+        assert_round_trip(json!({"hosts": [1, 2]}), json!({"hosts": [1, null]}));
+    }
+
+    #[test]
+    fn round_trips_nulls() {
+        assert_round_trip(json!({"a": 1, "b": 2}), json!({"a": null, "b": 2}));
+        assert_round_trip(json!({"a": null}), json!({}));
+        assert_round_trip(json!({}), json!({"a": null}));
+        assert_round_trip(json!({"a": null}), json!({"a": 1}));
+        assert_round_trip(json!({"a": 1, "b": 2}), json!({"b": 2}));
+    }
+
+    #[test]
+    fn applies_dotted_diff_to_existing_dotted_key() {
+        let mut target =
+            json!({"annotations": {"nginx.ingress.kubernetes.io/rewrite-target": "/"}});
+        let diff = JsonDiff::from([(
+            "annotations.nginx.ingress.kubernetes.io/rewrite-target".to_owned(),
+            (json!("/"), json!("/api")),
+        )]);
+        apply_json_diff(&mut target, &diff).unwrap();
+        assert_eq!(
+            target,
+            json!({"annotations": {"nginx.ingress.kubernetes.io/rewrite-target": "/api"}})
+        );
+    }
+
+    #[test]
+    fn detects_conflicts() {
+        let diff = json_diff(&json!({"a": 1}), &json!({"a": 2}));
+        let mut target = json!({"a": 3});
+        assert!(matches!(
+            apply_json_diff(&mut target, &diff),
+            Err(JsonDiffError::Conflict(path)) if path == "a"
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+use crate::{Metric, StatusColor};
+use rust_decimal::Decimal;
+use std::fmt;
+
+const BYTE_UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+/// Units for metrics, converting values to a readable scale, e.g. bytes to
+/// MiB or seconds to milliseconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MetricUnit {
+    /// A percentage between 0 and 100.
+    Percent,
+    Bytes,
+    RequestsPerSecond,
+    /// A duration in seconds.
+    Seconds,
+    Other(String),
+}
+
+impl MetricUnit {
+    /// Converts a value in this unit to a readable scale, returning the
+    /// scaled value rounded to two decimal places and the unit to show.
+    pub fn scale(&self, value: Decimal) -> (Decimal, String) {
+        let (value, unit) = match self {
+            Self::Bytes => {
+                let mut value = value;
+                let mut index = 0;
+                while value.abs() >= Decimal::from(1024) && index < BYTE_UNITS.len() - 1 {
+                    value /= Decimal::from(1024);
+                    index += 1;
+                }
+                (value, BYTE_UNITS[index].to_owned())
+            }
+            Self::Seconds if !value.is_zero() && value.abs() < Decimal::ONE => {
+                (value * Decimal::ONE_THOUSAND, "ms".to_owned())
+            }
+            Self::Seconds if value.abs() >= Decimal::from(3600) => {
+                (value / Decimal::from(3600), "h".to_owned())
+            }
+            Self::Seconds if value.abs() >= Decimal::from(60) => {
+                (value / Decimal::from(60), "min".to_owned())
+            }
+            _ => (value, self.to_string()),
+        };
+        (value.round_dp(2).normalize(), unit)
+    }
+
+    /// Formats a value in this unit, e.g. `1.5 KiB` or `42%`.
+    pub fn format(&self, value: Decimal) -> String {
+        match self.scale(value) {
+            (value, unit) if unit == "%" => format!("{value}{unit}"),
+            (value, unit) => format!("{value} {unit}"),
+        }
+    }
+}
+
+impl fmt::Display for MetricUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Percent => f.write_str("%"),
+            Self::Bytes => f.write_str("B"),
+            Self::RequestsPerSecond => f.write_str("req/s"),
+            Self::Seconds => f.write_str("s"),
+            Self::Other(unit) => f.write_str(unit),
+        }
+    }
+}
+
+/// Picks a metric's color by comparing its value to warning and danger
+/// levels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thresholds {
+    warning: Decimal,
+    danger: Decimal,
+    higher_is_worse: bool,
+}
+
+impl Thresholds {
+    /// Values at or above `warning` are a warning, and at or above `danger`
+    /// are a danger, e.g. for error rates or memory usage.
+    pub fn above(warning: Decimal, danger: Decimal) -> Self {
+        Self {
+            warning,
+            danger,
+            higher_is_worse: true,
+        }
+    }
+
+    /// Values at or below `warning` are a warning, and at or below `danger`
+    /// are a danger, e.g. for free disk space or healthy replicas.
+    pub fn below(warning: Decimal, danger: Decimal) -> Self {
+        Self {
+            warning,
+            danger,
+            higher_is_worse: false,
+        }
+    }
+
+    pub fn color(&self, value: Decimal) -> StatusColor {
+        let reached = |level: Decimal| {
+            if self.higher_is_worse {
+                value >= level
+            } else {
+                value <= level
+            }
+        };
+        if reached(self.danger) {
+            StatusColor::Danger
+        } else if reached(self.warning) {
+            StatusColor::Warning
+        } else {
+            StatusColor::Success
+        }
+    }
+
+    /// A metric colored by these thresholds. The thresholds are compared to
+    /// `value` before it's scaled, so they're in the unit's base unit, e.g.
+    /// bytes rather than MiB.
+    pub fn metric<D>(&self, value: Decimal, unit: &MetricUnit, short_description: D) -> Metric
+    where
+        D: Into<String>,
+    {
+        Metric::with_unit(value, unit, short_description).with_color(self.color(value))
+    }
+}
+
+impl Metric {
+    /// A metric with its value scaled to a readable unit, see
+    /// `MetricUnit::scale`.
+    pub fn with_unit<D>(value: Decimal, unit: &MetricUnit, short_description: D) -> Self
+    where
+        D: Into<String>,
+    {
+        let (value, unit) = unit.scale(value);
+        Self::new(value, unit, short_description)
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} {}",
+            self.short_description, self.value, self.unit
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scale(unit: &MetricUnit, value: i64) -> (Decimal, String) {
+        unit.scale(Decimal::from(value))
+    }
+
+    #[test]
+    fn scales_bytes_at_boundaries() {
+        assert_eq!(
+            scale(&MetricUnit::Bytes, 1023),
+            (Decimal::from(1023), "B".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Bytes, 1024),
+            (Decimal::ONE, "KiB".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Bytes, 1536 * 1024),
+            (Decimal::new(15, 1), "MiB".to_owned())
+        );
+        // TiB is the largest unit.
+        assert_eq!(
+            scale(&MetricUnit::Bytes, 1024_i64.pow(5)),
+            (Decimal::from(1024), "TiB".to_owned())
+        );
+    }
+
+    #[test]
+    fn scales_seconds_at_boundaries() {
+        assert_eq!(
+            MetricUnit::Seconds.scale(Decimal::new(25, 3)),
+            (Decimal::from(25), "ms".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Seconds, 0),
+            (Decimal::ZERO, "s".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Seconds, 1),
+            (Decimal::ONE, "s".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Seconds, 59),
+            (Decimal::from(59), "s".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Seconds, 60),
+            (Decimal::ONE, "min".to_owned())
+        );
+        assert_eq!(
+            scale(&MetricUnit::Seconds, 3600),
+            (Decimal::ONE, "h".to_owned())
+        );
+        assert_eq!(MetricUnit::Percent.format(Decimal::new(4225, 2)), "42.25%");
+        assert_eq!(MetricUnit::Bytes.format(Decimal::from(2048)), "2 KiB");
+    }
+
+    #[test]
+    fn thresholds_include_their_levels() {
+        let above = Thresholds::above(Decimal::from(80), Decimal::from(90));
+        assert_eq!(above.color(Decimal::new(7999, 2)), StatusColor::Success);
+        assert_eq!(above.color(Decimal::from(80)), StatusColor::Warning);
+        assert_eq!(above.color(Decimal::new(8999, 2)), StatusColor::Warning);
+        assert_eq!(above.color(Decimal::from(90)), StatusColor::Danger);
+
+        let below = Thresholds::below(Decimal::from(2), Decimal::ONE);
+        assert_eq!(below.color(Decimal::from(3)), StatusColor::Success);
+        assert_eq!(below.color(Decimal::from(2)), StatusColor::Warning);
+        assert_eq!(below.color(Decimal::ONE), StatusColor::Danger);
+        assert_eq!(below.color(Decimal::ZERO), StatusColor::Danger);
+    }
+
+    #[test]
+    fn threshold_metrics_compare_unscaled_values() {
+        let thresholds = Thresholds::above(Decimal::from(1024), Decimal::from(2048));
+        let metric = thresholds.metric(Decimal::from(1024), &MetricUnit::Bytes, "Memory");
+        assert_eq!((metric.value, metric.unit.as_str()), (Decimal::ONE, "KiB"));
+        assert_eq!(metric.color, Some(StatusColor::Warning));
+    }
+}
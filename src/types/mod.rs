@@ -1,3 +1,13 @@
 mod deployment_status;
+mod ids;
+mod json_diff;
+mod metric;
+mod prometheus;
+mod secret_string;
 
 pub use deployment_status::*;
+pub use ids::*;
+pub use json_diff::*;
+pub use metric::*;
+pub use prometheus::*;
+pub use secret_string::*;
@@ -0,0 +1,285 @@
+use crate::{Metric, MetricUnit, Thresholds};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, thiserror::Error)]
+#[error("Error parsing Prometheus metrics on line {line}: {reason}")]
+pub struct PrometheusParseError {
+    pub line: usize,
+    pub reason: &'static str,
+}
+
+/// A single sample from a Prometheus text exposition.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrometheusSample {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
+
+/// Samples parsed from a Prometheus text exposition, e.g. the body of a
+/// service's `/metrics` endpoint, for turning them into Platz metrics.
+#[derive(Debug, Clone, Default)]
+pub struct PrometheusSnapshot {
+    pub samples: Vec<PrometheusSample>,
+}
+
+impl PrometheusSnapshot {
+    /// Parse the text exposition format. Comments, `HELP` and `TYPE` lines
+    /// and timestamps are ignored.
+    pub fn parse(text: &str) -> Result<Self, PrometheusParseError> {
+        let samples = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| {
+                let line = line.trim();
+                !line.is_empty() && !line.starts_with('#')
+            })
+            .map(|(index, line)| {
+                parse_sample(line.trim()).map_err(|reason| PrometheusParseError {
+                    line: index + 1,
+                    reason,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { samples })
+    }
+
+    /// Samples named `name` whose labels include all of `labels`.
+    pub fn samples<'a>(
+        &'a self,
+        name: &'a str,
+        labels: &'a [(&'a str, &'a str)],
+    ) -> impl Iterator<Item = &'a PrometheusSample> {
+        self.samples.iter().filter(move |sample| {
+            sample.name == name
+                && labels
+                    .iter()
+                    .all(|(key, value)| sample.labels.get(*key).map(String::as_str) == Some(value))
+        })
+    }
+
+    /// The sum of the matching samples' values, or `None` if there are no
+    /// matching samples or the sum isn't a finite number.
+    pub fn value(&self, name: &str, labels: &[(&str, &str)]) -> Option<Decimal> {
+        let mut matched = false;
+        let sum: f64 = self
+            .samples(name, labels)
+            .inspect(|_| matched = true)
+            .map(|sample| sample.value)
+            .sum();
+        if matched {
+            Decimal::try_from(sum).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The per second rate of a counter between an `earlier` snapshot and
+    /// this one, taken `elapsed` apart. A counter reset counts as a rate
+    /// from zero.
+    pub fn rate(
+        &self,
+        earlier: &Self,
+        name: &str,
+        labels: &[(&str, &str)],
+        elapsed: Duration,
+    ) -> Option<Decimal> {
+        let current = self.value(name, labels)?;
+        let previous = earlier.value(name, labels).unwrap_or_default();
+        let seconds = Decimal::try_from(elapsed.as_secs_f64()).ok()?;
+        if seconds.is_zero() {
+            return None;
+        }
+        let increase = if current >= previous {
+            current - previous
+        } else {
+            current
+        };
+        Some(increase / seconds)
+    }
+
+    /// A metric with the sum of the matching samples' values.
+    pub fn metric<D>(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        unit: &MetricUnit,
+        short_description: D,
+    ) -> Option<Metric>
+    where
+        D: Into<String>,
+    {
+        Some(Metric::with_unit(
+            self.value(name, labels)?,
+            unit,
+            short_description,
+        ))
+    }
+
+    /// Same as `metric`, colored by `thresholds`.
+    pub fn metric_with_thresholds<D>(
+        &self,
+        name: &str,
+        labels: &[(&str, &str)],
+        unit: &MetricUnit,
+        short_description: D,
+        thresholds: &Thresholds,
+    ) -> Option<Metric>
+    where
+        D: Into<String>,
+    {
+        Some(thresholds.metric(self.value(name, labels)?, unit, short_description))
+    }
+}
+
+fn parse_sample(line: &str) -> Result<PrometheusSample, &'static str> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .ok_or("missing value")?;
+    let name = &line[..name_end];
+    if name.is_empty() {
+        return Err("missing metric name");
+    }
+
+    let mut labels = BTreeMap::new();
+    let mut rest = &line[name_end..];
+    if let Some(label_text) = rest.strip_prefix('{') {
+        rest = parse_labels(label_text, &mut labels)?;
+    }
+
+    let value = rest.split_whitespace().next().ok_or("missing value")?;
+    let value = match value {
+        "+Inf" => f64::INFINITY,
+        "-Inf" => f64::NEG_INFINITY,
+        value => value.parse().map_err(|_| "invalid value")?,
+    };
+    Ok(PrometheusSample {
+        name: name.to_owned(),
+        labels,
+        value,
+    })
+}
+
+/// Parses labels up to the closing brace, returning the rest of the line.
+fn parse_labels<'a>(
+    mut text: &'a str,
+    labels: &mut BTreeMap<String, String>,
+) -> Result<&'a str, &'static str> {
+    loop {
+        text = text.trim_start();
+        if let Some(rest) = text.strip_prefix('}') {
+            return Ok(rest);
+        }
+        let (key, rest) = text.split_once('=').ok_or("invalid label")?;
+        let mut chars = rest
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or("invalid label")?
+            .chars();
+        let mut value = String::new();
+        loop {
+            match chars.next().ok_or("unterminated label value")? {
+                '"' => break,
+                '\\' => match chars.next().ok_or("unterminated label value")? {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                c => value.push(c),
+            }
+        }
+        labels.insert(key.trim().to_owned(), value);
+        text = chars.as_str().trim_start();
+        text = text.strip_prefix(',').unwrap_or(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPOSITION: &str = r#"
+# HELP http_requests_total Requests handled.
+# TYPE http_requests_total counter
+http_requests_total{method="GET",code="200"} 1027 1395066363000
+http_requests_total{method="POST",code="500"} 3 1395066363000
+# A comment that isn't HELP or TYPE
+queue_depth 12
+latency_bucket{le="+Inf"} +Inf
+ratio NaN
+path_hits{path="C:\\DIR\\FILE.TXT",quote="say \"hi\"",text="a\nb"} 1.5e3
+"#;
+
+    #[test]
+    fn parses_exposition() {
+        let snapshot = PrometheusSnapshot::parse(EXPOSITION).unwrap();
+        assert_eq!(snapshot.samples.len(), 6);
+        assert_eq!(
+            snapshot.value("http_requests_total", &[]),
+            Some(Decimal::from(1030))
+        );
+        assert_eq!(
+            snapshot.value("http_requests_total", &[("code", "500")]),
+            Some(Decimal::from(3))
+        );
+        assert_eq!(snapshot.value("queue_depth", &[]), Some(Decimal::from(12)));
+        assert_eq!(snapshot.value("missing", &[]), None);
+    }
+
+    #[test]
+    fn parses_escaped_label_values() {
+        let snapshot = PrometheusSnapshot::parse(EXPOSITION).unwrap();
+        let sample = snapshot.samples("path_hits", &[]).next().unwrap();
+        assert_eq!(sample.labels["path"], r"C:\DIR\FILE.TXT");
+        assert_eq!(sample.labels["quote"], r#"say "hi""#);
+        assert_eq!(sample.labels["text"], "a\nb");
+        assert_eq!(sample.value, 1500.0);
+    }
+
+    #[test]
+    fn parses_special_values() {
+        let snapshot = PrometheusSnapshot::parse(EXPOSITION).unwrap();
+        let inf = snapshot.samples("latency_bucket", &[("le", "+Inf")]).next();
+        assert_eq!(inf.unwrap().value, f64::INFINITY);
+        assert!(snapshot
+            .samples("ratio", &[])
+            .next()
+            .unwrap()
+            .value
+            .is_nan());
+        // Neither is a finite number.
+        assert_eq!(snapshot.value("latency_bucket", &[]), None);
+        assert_eq!(snapshot.value("ratio", &[]), None);
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let err = PrometheusSnapshot::parse("# TYPE up gauge\nup{job=\"a\" 1\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        let err = PrometheusSnapshot::parse("up{job=\"a} 1").unwrap_err();
+        assert_eq!(err.reason, "unterminated label value");
+        let err = PrometheusSnapshot::parse("up one").unwrap_err();
+        assert_eq!(err.reason, "invalid value");
+    }
+
+    #[test]
+    fn rate_handles_counter_resets() {
+        let earlier = PrometheusSnapshot::parse("requests_total 100").unwrap();
+        let later = PrometheusSnapshot::parse("requests_total 160").unwrap();
+        let reset = PrometheusSnapshot::parse("requests_total 30").unwrap();
+        let elapsed = Duration::from_secs(60);
+        assert_eq!(
+            later.rate(&earlier, "requests_total", &[], elapsed),
+            Some(Decimal::ONE)
+        );
+        assert_eq!(
+            reset.rate(&earlier, "requests_total", &[], elapsed),
+            Some(Decimal::new(5, 1))
+        );
+        assert_eq!(
+            later.rate(&earlier, "requests_total", &[], Duration::ZERO),
+            None
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use zeroize::Zeroize;
+
+/// A string holding a token or secret contents. It's redacted when
+/// formatted with `Debug` or `Display` and its memory is zeroed on drop.
+/// Use `expose_secret` to access the actual value.
+#[derive(Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new<S>(value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self(value.into())
+    }
+
+    pub fn expose_secret(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString([REDACTED])")
+    }
+}
+
+impl fmt::Display for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_when_formatted() {
+        let secret = SecretString::new("hunter2");
+        assert_eq!(format!("{secret}"), "[REDACTED]");
+        assert_eq!(format!("{secret:?}"), "SecretString([REDACTED])");
+
+        #[derive(Debug)]
+        #[allow(dead_code)]
+        struct Credentials {
+            token: SecretString,
+        }
+        let credentials = format!("{:?}", Credentials { token: secret });
+        assert!(!credentials.contains("hunter2"), "{credentials}");
+    }
+
+    #[test]
+    fn keeps_value_when_exposed_or_serialized() {
+        let secret = SecretString::from("hunter2");
+        assert_eq!(secret.expose_secret(), "hunter2");
+        assert_eq!(serde_json::to_value(&secret).unwrap(), json!("hunter2"));
+
+        let secret: SecretString = serde_json::from_value(json!("hunter2")).unwrap();
+        assert_eq!(secret, SecretString::from("hunter2".to_owned()));
+        assert_eq!(secret.clone().expose_secret(), "hunter2");
+    }
+}